@@ -4,8 +4,11 @@ extern crate criterion;
 extern crate weighted_trie;
 
 use criterion::{criterion_group, criterion_main, Criterion};
+use std::cmp::Reverse;
 use std::fs;
 use std::path::Path;
+use weighted_trie::FrozenTrie;
+use weighted_trie::LazyTrie;
 use weighted_trie::WeightedString;
 use weighted_trie::WeightedTrie;
 
@@ -13,7 +16,7 @@ lazy_static! {
     static ref DATA: Vec<WeightedString> = {
         let mut data = Vec::new();
         let path = Path::new("/tmp/data/benchmark/weighted_strings.txt");
-        let contens: String = fs::read_to_string(&path).unwrap();
+        let contens: String = fs::read_to_string(path).unwrap();
         for line in contens.lines().take(100000) {
             let line_splitted: Vec<&str> = line.split('\t').collect();
             data.push(WeightedString {
@@ -25,7 +28,7 @@ lazy_static! {
     };
     static ref TRIE: WeightedTrie = {
         let path = Path::new("/tmp/data/benchmark/weighted_strings.txt");
-        let contens: String = fs::read_to_string(&path).unwrap();
+        let contens: String = fs::read_to_string(path).unwrap();
         let mut trie = weighted_trie::WeightedTrie::new();
         for line in contens.lines().take(100000) {
             let line_splitted: Vec<&str> = line.split('\t').collect();
@@ -35,13 +38,26 @@ lazy_static! {
         }
         trie
     };
+    static ref FROZEN: FrozenTrie = {
+        let path = Path::new("/tmp/data/benchmark/weighted_strings.txt");
+        let contens: String = fs::read_to_string(path).unwrap();
+        let mut trie = weighted_trie::WeightedTrie::new();
+        for line in contens.lines().take(100000) {
+            let line_splitted: Vec<&str> = line.split('\t').collect();
+            let string = line_splitted[0].to_owned();
+            let weight = line_splitted[1].parse::<i32>().unwrap();
+            trie.insert(string, weight);
+        }
+        trie.freeze()
+    };
+    static ref LAZY: LazyTrie = LazyTrie::build(DATA.clone());
 }
 
 fn insert() {
     // Note: to get a benchmark data
     // wget https://gist.githubusercontent.com/subpath/c19778c9549e5dde02a405dd97fa7014/raw/6fe9433996607be9ceca6dc29e1d88582d64f5d1/weighted_strings.txt -P /tmp/data/benchmark
     let path = Path::new("/tmp/data/benchmark/weighted_strings.txt");
-    let contens: String = fs::read_to_string(&path).unwrap();
+    let contens: String = fs::read_to_string(path).unwrap();
     let mut trie = weighted_trie::WeightedTrie::new();
     for line in contens.lines().take(100000) {
         let line_splitted: Vec<&str> = line.split('\t').collect();
@@ -51,6 +67,13 @@ fn insert() {
     }
 }
 
+fn insert_equal_weights() {
+    let mut trie = weighted_trie::WeightedTrie::new();
+    for ws in DATA.iter().take(50000) {
+        trie.insert(ws.word.clone(), 1);
+    }
+}
+
 fn lookup() {
     TRIE.search("pi");
     TRIE.search("pis");
@@ -58,10 +81,48 @@ fn lookup() {
     TRIE.search("pineapple");
 }
 
+fn lookup_single_char() {
+    TRIE.search("p");
+    TRIE.search("s");
+}
+
+fn lookup_single_char_top_k() {
+    TRIE.search_top_k("p", 10);
+    TRIE.search_top_k("s", 10);
+}
+
+fn lookup_top_k() {
+    TRIE.search_top_k("pi", 10);
+    TRIE.search_top_k("pis", 10);
+    TRIE.search_top_k("p", 10);
+    TRIE.search_top_k("pineapple", 10);
+}
+
+fn lookup_top_k_lazy() {
+    LAZY.search_top_k("pi", 10);
+    LAZY.search_top_k("pis", 10);
+    LAZY.search_top_k("p", 10);
+    LAZY.search_top_k("pineapple", 10);
+}
+
+fn walk_ascii() {
+    TRIE.max_weight("pineapple");
+    TRIE.max_weight("pis");
+    TRIE.max_weight("strawberry");
+    TRIE.max_weight("internationalization");
+}
+
+fn lookup_frozen() {
+    FROZEN.search("pi");
+    FROZEN.search("pis");
+    FROZEN.search("p");
+    FROZEN.search("pineapple");
+}
+
 fn build() {
     let mut data = Vec::new();
     let path = Path::new("/tmp/data/benchmark/weighted_strings.txt");
-    let contens: String = fs::read_to_string(&path).unwrap();
+    let contens: String = fs::read_to_string(path).unwrap();
     for line in contens.lines().take(100000) {
         let line_splitted: Vec<&str> = line.split('\t').collect();
         data.push(WeightedString {
@@ -73,12 +134,54 @@ fn build() {
     let _trie = WeightedTrie::build(data);
 }
 
+fn build_presorted() {
+    let mut data = Vec::new();
+    let path = Path::new("/tmp/data/benchmark/weighted_strings.txt");
+    let contens: String = fs::read_to_string(path).unwrap();
+    for line in contens.lines().take(100000) {
+        let line_splitted: Vec<&str> = line.split('\t').collect();
+        data.push(WeightedString {
+            word: line_splitted[0].to_owned(),
+            weight: line_splitted[1].parse::<i32>().unwrap(),
+        })
+    }
+    data.sort_by_key(|ws| Reverse(ws.weight));
+
+    let _trie = WeightedTrie::build_presorted(data);
+}
+
+fn freeze_sorted() {
+    let mut data = DATA.clone();
+    data.sort_by(|a, b| a.word.cmp(&b.word));
+
+    let _frozen = WeightedTrie::build(data).freeze();
+}
+
+fn from_sorted() {
+    let mut data = DATA.clone();
+    data.sort_by(|a, b| a.word.cmp(&b.word));
+
+    let _frozen = FrozenTrie::from_sorted(data);
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("weighted_trie");
     group.sample_size(10);
-    group.bench_function("insert", |b| b.iter(|| insert()));
-    group.bench_function("lookup", |b| b.iter(|| lookup()));
-    group.bench_function("build", |b| b.iter(|| build()));
+    group.bench_function("insert", |b| b.iter(insert));
+    group.bench_function("insert_equal_weights", |b| b.iter(insert_equal_weights));
+    group.bench_function("lookup", |b| b.iter(lookup));
+    group.bench_function("lookup_single_char", |b| b.iter(lookup_single_char));
+    group.bench_function("lookup_single_char_top_k", |b| {
+        b.iter(lookup_single_char_top_k)
+    });
+    group.bench_function("lookup_top_k", |b| b.iter(lookup_top_k));
+    group.bench_function("lookup_top_k_lazy", |b| b.iter(lookup_top_k_lazy));
+    group.bench_function("walk_ascii", |b| b.iter(walk_ascii));
+    group.bench_function("lookup_frozen", |b| b.iter(lookup_frozen));
+    group.bench_function("build", |b| b.iter(build));
+    group.bench_function("build_presorted", |b| b.iter(build_presorted));
+    group.bench_function("freeze_sorted", |b| b.iter(freeze_sorted));
+    group.bench_function("from_sorted", |b| b.iter(from_sorted));
 }
 
 criterion_group!(benches, criterion_benchmark);
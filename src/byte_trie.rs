@@ -0,0 +1,62 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+#[cfg(feature = "std")]
+type ByteChildren = HashMap<u8, ByteTrieNode>;
+#[cfg(not(feature = "std"))]
+type ByteChildren = BTreeMap<u8, ByteTrieNode>;
+
+#[derive(Debug, Default, PartialEq)]
+struct ByteTrieNode {
+    children: ByteChildren,
+    suggestions: Vec<(i32, Vec<u8>)>,
+}
+
+/// A [`WeightedTrie`](crate::WeightedTrie) keyed on bytes, for keys that are
+/// not valid UTF-8.
+#[derive(Debug, Default, PartialEq)]
+pub struct ByteTrie {
+    root: ByteTrieNode,
+}
+
+impl ByteTrie {
+    pub fn new() -> ByteTrie {
+        ByteTrie::default()
+    }
+
+    pub fn build(weighted_keys: Vec<(Vec<u8>, i32)>) -> ByteTrie {
+        let mut trie = ByteTrie::new();
+        weighted_keys
+            .into_iter()
+            .for_each(|(key, weight)| trie.insert(&key, weight));
+        trie
+    }
+
+    pub fn insert(&mut self, key: &[u8], weight: i32) {
+        let mut node = &mut self.root;
+        for &b in key {
+            node = node.children.entry(b).or_default();
+            let pos = node.suggestions.partition_point(|&(w, _)| w >= weight);
+            node.suggestions.insert(pos, (weight, key.to_vec()));
+        }
+    }
+
+    pub fn search(&self, prefix: &[u8]) -> Vec<Vec<u8>> {
+        let mut node = &self.root;
+        for b in prefix {
+            match node.children.get(b) {
+                Some(child) => node = child,
+                None => return vec![],
+            }
+        }
+        node.suggestions
+            .iter()
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,38 @@
+use core::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrieError {
+    /// The word is empty, after normalization if the trie normalizes words.
+    EmptyWord,
+    /// The word has more chars than the trie's configured maximum.
+    WordTooLong { len: usize, max: usize },
+    /// The word is already stored and the operation does not allow duplicates.
+    DuplicateWord,
+    /// The word would give a node more children than the trie's configured
+    /// maximum.
+    TooManyChildren { max: usize },
+    /// The bytes are not a trie serialized by `FrozenTrie::to_bytes`.
+    InvalidBytes,
+}
+
+impl fmt::Display for TrieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrieError::EmptyWord => write!(f, "word is empty"),
+            TrieError::WordTooLong { len, max } => {
+                write!(f, "word is {len} chars long, the maximum is {max}")
+            }
+            TrieError::DuplicateWord => write!(f, "word is already in the trie"),
+            TrieError::TooManyChildren { max } => {
+                write!(
+                    f,
+                    "word would exceed the maximum of {max} children per node"
+                )
+            }
+            TrieError::InvalidBytes => write!(f, "bytes are not a serialized trie"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TrieError {}
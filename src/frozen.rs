@@ -0,0 +1,432 @@
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::TrieError;
+use crate::trie::{ChildHasher, KeyOptions, PrefixSearch, TrieNode, WeightedString, WeightedTrie};
+
+const MAGIC: &[u8; 4] = b"WTRI";
+const VERSION: u32 = 2;
+// magic, then version, key flags, min query len and the four buffer lengths as u32s
+const HEADER_LEN: usize = 4 + 7 * 4;
+const NODE_LEN: usize = 16;
+const EDGE_LEN: usize = 8;
+const SUGGESTION_LEN: usize = 12;
+
+#[derive(Debug)]
+struct FrozenNode {
+    edges: (u32, u32),
+    suggestions: (u32, u32),
+}
+
+#[derive(Debug)]
+struct FrozenStorage {
+    nodes: Vec<FrozenNode>,
+    // children of every node, sorted by char within each node's range
+    edges: Vec<(char, u32)>,
+    // (weight, start, end) into `text`; a node's words are laid out next to
+    // each other so reading its suggestions is a sequential scan
+    suggestions: Vec<(i32, u32, u32)>,
+    text: String,
+    key: KeyOptions,
+}
+
+/// Read-only trie produced by [`WeightedTrie::freeze`].
+///
+/// All nodes and words live in a few flat buffers addressed by index, and
+/// cloning only bumps a reference count.
+#[derive(Clone, Debug)]
+pub struct FrozenTrie {
+    storage: Arc<FrozenStorage>,
+}
+
+impl FrozenTrie {
+    pub(crate) fn from_trie<S: ChildHasher>(trie: &WeightedTrie<S>) -> FrozenTrie {
+        let mut storage = FrozenStorage {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            suggestions: Vec::new(),
+            text: String::new(),
+            key: trie.key_options(),
+        };
+        let mut queue: VecDeque<TrieNode<S>> = VecDeque::from([trie.root()]);
+        // nodes are numbered in BFS order, so the child ids handed out below
+        // match the order in which the children are popped from the queue
+        let mut next_id = 1;
+        while let Some(node) = queue.pop_front() {
+            let suggestions_start = storage.suggestions.len() as u32;
+            for (weight, word) in node.suggestions() {
+                let start = storage.text.len() as u32;
+                storage.text.push_str(word);
+                storage
+                    .suggestions
+                    .push((*weight, start, storage.text.len() as u32));
+            }
+
+            let edges_start = storage.edges.len() as u32;
+            let mut children: Vec<(char, TrieNode<S>)> = node.children().collect();
+            children.sort_by_key(|&(c, _)| c);
+            for (c, child) in children {
+                storage.edges.push((c, next_id));
+                next_id += 1;
+                queue.push_back(child);
+            }
+
+            storage.nodes.push(FrozenNode {
+                edges: (edges_start, storage.edges.len() as u32),
+                suggestions: (suggestions_start, storage.suggestions.len() as u32),
+            });
+        }
+        storage.nodes.shrink_to_fit();
+        storage.edges.shrink_to_fit();
+        storage.suggestions.shrink_to_fit();
+        storage.text.shrink_to_fit();
+        FrozenTrie {
+            storage: Arc::new(storage),
+        }
+    }
+
+    /// Builds a frozen trie in one streaming pass over words sorted
+    /// lexicographically, such as the lines of a sorted dictionary file,
+    /// without building a [`WeightedTrie`] first.
+    ///
+    /// Only the nodes along the current word are kept open. The common prefix
+    /// with the previous word tells how many of them are complete, and those
+    /// are written out with their subtree's suggestions merged into their
+    /// parent's. Each word's text is stored once and shared by all its nodes.
+    /// Searches match [`WeightedTrie::build`] over the same words. Unsorted
+    /// input is caught by a debug assertion, in release builds it silently
+    /// produces a broken trie.
+    pub fn from_sorted(weighted_strings: impl IntoIterator<Item = WeightedString>) -> FrozenTrie {
+        struct OpenNode {
+            c: char,
+            edges: Vec<(char, u32)>,
+            suggestions: Vec<(i32, u32, u32)>,
+        }
+
+        fn close(storage: &mut FrozenStorage, stack: &mut Vec<OpenNode>) {
+            let Some(node) = stack.pop() else { return };
+            let edges_start = storage.edges.len() as u32;
+            storage.edges.extend_from_slice(&node.edges);
+            let suggestions_start = storage.suggestions.len() as u32;
+            storage.suggestions.extend_from_slice(&node.suggestions);
+            storage.nodes.push(FrozenNode {
+                edges: (edges_start, storage.edges.len() as u32),
+                suggestions: (suggestions_start, storage.suggestions.len() as u32),
+            });
+            let id = storage.nodes.len() as u32 - 1;
+            if let Some(parent) = stack.last_mut() {
+                parent.edges.push((node.c, id));
+                // ties keep input order, and the parent's entries came first
+                let mut merged =
+                    Vec::with_capacity(parent.suggestions.len() + node.suggestions.len());
+                let mut theirs = node.suggestions.into_iter().peekable();
+                for entry in parent.suggestions.drain(..) {
+                    while let Some(next) = theirs.next_if(|next| next.0 > entry.0) {
+                        merged.push(next);
+                    }
+                    merged.push(entry);
+                }
+                merged.extend(theirs);
+                parent.suggestions = merged;
+            }
+        }
+
+        let mut storage = FrozenStorage {
+            // the root is written to slot 0 once every other node is closed
+            nodes: vec![FrozenNode {
+                edges: (0, 0),
+                suggestions: (0, 0),
+            }],
+            edges: Vec::new(),
+            suggestions: Vec::new(),
+            text: String::new(),
+            key: KeyOptions::default(),
+        };
+        let mut stack = vec![OpenNode {
+            c: '\0',
+            edges: Vec::new(),
+            suggestions: Vec::new(),
+        }];
+        let mut previous = (0, 0);
+        for ws in weighted_strings {
+            if ws.word.is_empty() {
+                continue;
+            }
+            let prev = &storage.text[previous.0..previous.1];
+            debug_assert!(
+                prev <= ws.word.as_str(),
+                "from_sorted input must be sorted by word"
+            );
+            let common = prev
+                .chars()
+                .zip(ws.word.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            while stack.len() > common + 1 {
+                close(&mut storage, &mut stack);
+            }
+            let start = storage.text.len();
+            storage.text.push_str(&ws.word);
+            previous = (start, storage.text.len());
+            stack.extend(ws.word.chars().skip(common).map(|c| OpenNode {
+                c,
+                edges: Vec::new(),
+                suggestions: Vec::new(),
+            }));
+            let entry = (ws.weight, start as u32, previous.1 as u32);
+            if let Some(node) = stack.last_mut() {
+                let pos = node
+                    .suggestions
+                    .partition_point(|&(w, _, _)| w >= ws.weight);
+                node.suggestions.insert(pos, entry);
+            }
+        }
+        while stack.len() > 1 {
+            close(&mut storage, &mut stack);
+        }
+        // the root keeps no suggestions of its own, like `WeightedTrie`
+        if let Some(root) = stack.pop() {
+            let edges_start = storage.edges.len() as u32;
+            storage.edges.extend_from_slice(&root.edges);
+            storage.nodes[0].edges = (edges_start, storage.edges.len() as u32);
+        }
+        storage.nodes.shrink_to_fit();
+        storage.edges.shrink_to_fit();
+        storage.suggestions.shrink_to_fit();
+        storage.text.shrink_to_fit();
+        FrozenTrie {
+            storage: Arc::new(storage),
+        }
+    }
+
+    /// Serializes the trie into a self-contained buffer that
+    /// [`SearchableBytes::from_bytes`] can search in place.
+    ///
+    /// The buffer is the header followed by the nodes, edges, suggestions and
+    /// text of the trie, with every integer stored as 4 little-endian bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let storage = &self.storage;
+        let mut out = Vec::with_capacity(
+            HEADER_LEN
+                + storage.nodes.len() * NODE_LEN
+                + storage.edges.len() * EDGE_LEN
+                + storage.suggestions.len() * SUGGESTION_LEN
+                + storage.text.len(),
+        );
+        out.extend_from_slice(MAGIC);
+        let header = [
+            VERSION,
+            storage.key.flags(),
+            storage.key.min_query_len as u32,
+            storage.nodes.len() as u32,
+            storage.edges.len() as u32,
+            storage.suggestions.len() as u32,
+            storage.text.len() as u32,
+        ];
+        for field in header {
+            out.extend_from_slice(&field.to_le_bytes());
+        }
+        for node in &storage.nodes {
+            let (edges, suggestions) = (node.edges, node.suggestions);
+            for field in [edges.0, edges.1, suggestions.0, suggestions.1] {
+                out.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        for &(c, id) in &storage.edges {
+            out.extend_from_slice(&(c as u32).to_le_bytes());
+            out.extend_from_slice(&id.to_le_bytes());
+        }
+        for &(weight, start, end) in &storage.suggestions {
+            out.extend_from_slice(&weight.to_le_bytes());
+            out.extend_from_slice(&start.to_le_bytes());
+            out.extend_from_slice(&end.to_le_bytes());
+        }
+        out.extend_from_slice(storage.text.as_bytes());
+        out
+    }
+
+    pub fn search(&self, prefix: &str) -> Vec<String> {
+        self.suggestions(prefix)
+            .iter()
+            .map(|&(_, start, end)| String::from(self.word(start, end)))
+            .collect()
+    }
+
+    pub fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)> {
+        self.suggestions(prefix)
+            .iter()
+            .take(k)
+            .map(|&(weight, start, end)| (String::from(self.word(start, end)), weight))
+            .collect()
+    }
+
+    /// The `(weight, start, end)` entries of `prefix`, empty if it has none.
+    fn suggestions(&self, prefix: &str) -> &[(i32, u32, u32)] {
+        let storage = &self.storage;
+        if storage.key.shorter_than_min_query(prefix) {
+            return &[];
+        }
+        let mut node = &storage.nodes[0];
+        for c in storage.key.key(prefix).chars() {
+            let edges = &storage.edges[node.edges.0 as usize..node.edges.1 as usize];
+            match edges.binary_search_by_key(&c, |&(edge, _)| edge) {
+                Ok(i) => node = &storage.nodes[edges[i].1 as usize],
+                Err(_) => return &[],
+            }
+        }
+        &storage.suggestions[node.suggestions.0 as usize..node.suggestions.1 as usize]
+    }
+
+    fn word(&self, start: u32, end: u32) -> &str {
+        &self.storage.text[start as usize..end as usize]
+    }
+}
+
+impl PrefixSearch for FrozenTrie {
+    fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)> {
+        FrozenTrie::search_top_k(self, prefix, k)
+    }
+}
+
+/// A trie serialized by [`FrozenTrie::to_bytes`], searched in place without
+/// copying it into an owned tree, so the buffer can come straight from a
+/// memory-mapped file.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchableBytes<'a> {
+    nodes: &'a [u8],
+    edges: &'a [u8],
+    suggestions: &'a [u8],
+    text: &'a str,
+    key: KeyOptions,
+}
+
+impl<'a> SearchableBytes<'a> {
+    /// Checks that `bytes` is a well formed serialized trie and wraps it.
+    ///
+    /// Every index is validated up front, so searching never panics; this
+    /// takes time linear in the buffer but doesn't copy it.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<SearchableBytes<'a>, TrieError> {
+        let header = bytes.get(..HEADER_LEN).ok_or(TrieError::InvalidBytes)?;
+        if &header[..4] != MAGIC || read_u32(header, 4) != VERSION {
+            return Err(TrieError::InvalidBytes);
+        }
+        let key = KeyOptions::from_flags(read_u32(header, 8), read_u32(header, 12) as usize)
+            .ok_or(TrieError::InvalidBytes)?;
+        let node_count = read_u32(header, 16) as usize;
+        let edge_count = read_u32(header, 20) as usize;
+        let suggestion_count = read_u32(header, 24) as usize;
+        let text_len = read_u32(header, 28) as usize;
+
+        let mut rest = &bytes[HEADER_LEN..];
+        let mut take = |len: Option<usize>| -> Result<&'a [u8], TrieError> {
+            let len = len
+                .filter(|&len| len <= rest.len())
+                .ok_or(TrieError::InvalidBytes)?;
+            let (taken, remaining) = rest.split_at(len);
+            rest = remaining;
+            Ok(taken)
+        };
+        let nodes = take(node_count.checked_mul(NODE_LEN))?;
+        let edges = take(edge_count.checked_mul(EDGE_LEN))?;
+        let suggestions = take(suggestion_count.checked_mul(SUGGESTION_LEN))?;
+        let text = take(Some(text_len))?;
+        if !rest.is_empty() || node_count == 0 {
+            return Err(TrieError::InvalidBytes);
+        }
+        let text = core::str::from_utf8(text).map_err(|_| TrieError::InvalidBytes)?;
+
+        let in_range = |start: u32, end: u32, len: usize| start <= end && end as usize <= len;
+        let nodes_valid = nodes.chunks_exact(NODE_LEN).all(|node| {
+            in_range(read_u32(node, 0), read_u32(node, 4), edge_count)
+                && in_range(read_u32(node, 8), read_u32(node, 12), suggestion_count)
+        });
+        let edges_valid = edges
+            .chunks_exact(EDGE_LEN)
+            .all(|edge| (read_u32(edge, 4) as usize) < node_count);
+        let suggestions_valid = suggestions.chunks_exact(SUGGESTION_LEN).all(|entry| {
+            let (start, end) = (read_u32(entry, 4), read_u32(entry, 8));
+            in_range(start, end, text_len)
+                && text.is_char_boundary(start as usize)
+                && text.is_char_boundary(end as usize)
+        });
+        if !(nodes_valid && edges_valid && suggestions_valid) {
+            return Err(TrieError::InvalidBytes);
+        }
+        Ok(SearchableBytes {
+            nodes,
+            edges,
+            suggestions,
+            text,
+            key,
+        })
+    }
+
+    /// Same as [`FrozenTrie::search`], borrowing the words from the buffer.
+    pub fn search(&self, prefix: &str) -> Vec<&'a str> {
+        self.entries(prefix).map(|(_, word)| word).collect()
+    }
+
+    pub fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)> {
+        self.entries(prefix)
+            .take(k)
+            .map(|(weight, word)| (String::from(word), weight))
+            .collect()
+    }
+
+    /// The `(weight, word)` entries of `prefix`, empty if it has none.
+    fn entries(&self, prefix: &str) -> impl Iterator<Item = (i32, &'a str)> + '_ {
+        let mut node = Some(0).filter(|_| !self.key.shorter_than_min_query(prefix));
+        for c in self.key.key(prefix).chars() {
+            node = node.and_then(|node| self.child(node, c));
+        }
+        let (start, end) = node.map_or((0, 0), |node| self.range(node, 8));
+        (start..end).map(|i| {
+            let offset = i * SUGGESTION_LEN;
+            let weight = read_u32(self.suggestions, offset) as i32;
+            let start = read_u32(self.suggestions, offset + 4) as usize;
+            let end = read_u32(self.suggestions, offset + 8) as usize;
+            (weight, &self.text[start..end])
+        })
+    }
+
+    fn child(&self, node: usize, c: char) -> Option<usize> {
+        // edges are sorted by char within each node's range
+        let (mut lo, mut hi) = self.range(node, 0);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let edge = read_u32(self.edges, mid * EDGE_LEN);
+            match edge.cmp(&(c as u32)) {
+                core::cmp::Ordering::Less => lo = mid + 1,
+                core::cmp::Ordering::Greater => hi = mid,
+                core::cmp::Ordering::Equal => {
+                    return Some(read_u32(self.edges, mid * EDGE_LEN + 4) as usize)
+                }
+            }
+        }
+        None
+    }
+
+    /// The `[start, end)` pair stored at `field` of `node`.
+    fn range(&self, node: usize, field: usize) -> (usize, usize) {
+        let offset = node * NODE_LEN + field;
+        (
+            read_u32(self.nodes, offset) as usize,
+            read_u32(self.nodes, offset + 4) as usize,
+        )
+    }
+}
+
+impl PrefixSearch for SearchableBytes<'_> {
+    fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)> {
+        SearchableBytes::search_top_k(self, prefix, k)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
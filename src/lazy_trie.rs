@@ -0,0 +1,177 @@
+use alloc::collections::BinaryHeap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::trie::{PrefixSearch, WeightedString};
+
+#[cfg(feature = "std")]
+type LazyChildren = HashMap<char, LazyNode>;
+#[cfg(not(feature = "std"))]
+type LazyChildren = BTreeMap<char, LazyNode>;
+
+// higher ranks first: by weight, then by earlier insertion
+type Rank = (i32, Reverse<usize>);
+
+#[derive(Debug, Default, PartialEq)]
+struct LazyNode {
+    children: LazyChildren,
+    // the words ending here with their insertion sequence number
+    words: Vec<(i32, usize, String)>,
+    // best rank in the subtree, so a search can skip subtrees that can't
+    // make the top k
+    best: Option<Rank>,
+}
+
+/// A [`WeightedTrie`](crate::WeightedTrie) that stores each word once, at the
+/// node where it ends, instead of in the suggestion list of every prefix.
+///
+/// This removes the per-prefix copies that dominate the memory of a
+/// `WeightedTrie`, at the cost of finding the top completions at query time
+/// with a best-first walk of the prefix's subtree. Results and their order
+/// match a `WeightedTrie` with the default configuration.
+///
+/// ```
+/// use weighted_trie::LazyTrie;
+///
+/// let mut trie = LazyTrie::new();
+/// trie.insert("pie".to_owned(), 5);
+/// trie.insert("pizza".to_owned(), 10);
+/// trie.insert("pita".to_owned(), 2);
+/// assert_eq!(trie.search("pi"), vec!["pizza", "pie", "pita"]);
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct LazyTrie {
+    root: LazyNode,
+    next_seq: usize,
+}
+
+impl LazyTrie {
+    pub fn new() -> LazyTrie {
+        LazyTrie::default()
+    }
+
+    pub fn build(weighted_strings: Vec<WeightedString>) -> LazyTrie {
+        let mut trie = LazyTrie::new();
+        weighted_strings
+            .into_iter()
+            .for_each(|ws| trie.insert(ws.word, ws.weight));
+        trie
+    }
+
+    /// Inserts `word`, skipping it if it is empty. Inserting a word again
+    /// stores it again, as with [`WeightedTrie::insert`](crate::WeightedTrie::insert).
+    pub fn insert(&mut self, word: String, weight: i32) {
+        if word.is_empty() {
+            return;
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let rank = Some((weight, Reverse(seq)));
+        let mut node = &mut self.root;
+        for c in word.chars() {
+            node = node.children.entry(c).or_default();
+            node.best = node.best.max(rank);
+        }
+        node.words.push((weight, seq, word));
+    }
+
+    /// Returns every completion of `prefix`, ordered like
+    /// [`WeightedTrie::search`](crate::WeightedTrie::search).
+    pub fn search(&self, prefix: &str) -> Vec<String> {
+        self.search_top_k(prefix, usize::MAX)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect()
+    }
+
+    /// The `k` best completions of `prefix`, visiting only the subtrees
+    /// that can still contribute to them.
+    pub fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)> {
+        let mut node = &self.root;
+        for c in prefix.chars() {
+            match node.children.get(&c) {
+                Some(child) => node = child,
+                None => return vec![],
+            }
+        }
+        let mut heap = BinaryHeap::new();
+        // the root's best is never set, so like a `WeightedTrie` an empty
+        // prefix has no completions
+        if let Some(rank) = node.best {
+            heap.push(Candidate {
+                rank,
+                item: Item::Node(node),
+            });
+        }
+        let mut found = Vec::new();
+        while found.len() < k {
+            let Some(Candidate { rank, item }) = heap.pop() else {
+                break;
+            };
+            match item {
+                Item::Word(word) => found.push((String::from(word), rank.0)),
+                Item::Node(node) => {
+                    for (weight, seq, word) in &node.words {
+                        heap.push(Candidate {
+                            rank: (*weight, Reverse(*seq)),
+                            item: Item::Word(word),
+                        });
+                    }
+                    for child in node.children.values() {
+                        if let Some(rank) = child.best {
+                            heap.push(Candidate {
+                                rank,
+                                item: Item::Node(child),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+impl PrefixSearch for LazyTrie {
+    fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)> {
+        LazyTrie::search_top_k(self, prefix, k)
+    }
+}
+
+enum Item<'a> {
+    Node(&'a LazyNode),
+    Word(&'a str),
+}
+
+/// A heap entry, ordered by rank only.
+struct Candidate<'a> {
+    rank: Rank,
+    item: Item<'a>,
+}
+
+impl PartialEq for Candidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank == other.rank
+    }
+}
+
+impl Eq for Candidate<'_> {}
+
+impl PartialOrd for Candidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank.cmp(&other.rank)
+    }
+}
@@ -71,6 +71,24 @@
 //!
 //! ```
 //!
+//! ## Cargo features
+//! - `std` (default): use `std`. Without it the crate only needs `alloc` and
+//!   node children are kept in a `BTreeMap` instead of a `HashMap`.
+//! - `rand`: enables `WeightedTrie::sample` for weighted random completions.
+//! - `rayon`: enables `WeightedTrie::par_search` for answering a batch of
+//!   queries on a thread pool.
+//! - `cache`: enables `WeightedTrie::search_cached`, which memoizes the results
+//!   of popular prefixes until the trie is next modified.
+//! - `unicode-normalization`: enables `WeightedTrie::with_nfc`, which treats
+//!   canonically equivalent spellings of a word as the same word.
+//!
+//! To build for `no_std` targets disable the default features:
+//!
+//! ```toml
+//! [dependencies]
+//! weighted_trie = { version = "0.1.0", default-features = false }
+//! ```
+//!
 //! ## Benchmarks
 //! Using 100k weighted strings
 //!
@@ -94,6 +112,41 @@
 //!
 //!
 //! ## License
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use byte_trie::ByteTrie;
+pub use error::TrieError;
+pub use frozen::FrozenTrie;
+pub use frozen::SearchableBytes;
+pub use lazy_trie::LazyTrie;
+pub use trie::stable_id;
+pub use trie::word_char_len;
+pub use trie::BuildReport;
+pub use trie::ChunkedBuilder;
+pub use trie::DawgPotential;
+pub use trie::DedupPolicy;
+pub use trie::DepthStat;
+pub use trie::EvalReport;
+pub use trie::MergePolicy;
+pub use trie::PrefixSearch;
+pub use trie::QueryMetrics;
+pub use trie::SubTrie;
+pub use trie::TieBreak;
+pub use trie::TrieDiff;
+pub use trie::TsvOrder;
+pub use trie::ValidationIssue;
 pub use trie::WeightedString;
 pub use trie::WeightedTrie;
+pub use trie::WeightedTrieBuilder;
+pub use trie::WordEntry;
+#[cfg(feature = "std")]
+pub use wal::WriteAheadLog;
+pub mod byte_trie;
+pub mod error;
+pub mod frozen;
+pub mod lazy_trie;
 pub mod trie;
+#[cfg(feature = "std")]
+pub mod wal;
@@ -1,72 +1,3936 @@
-use std::cmp::Reverse;
+use alloc::borrow::{Cow, ToOwned};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+use core::fmt;
+use core::fmt::Write;
+use core::ops::Range;
+use core::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+use crate::error::TrieError;
+use crate::frozen::FrozenTrie;
+
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::collections::VecDeque;
+#[cfg(feature = "std")]
+use core::hash::BuildHasher;
+#[cfg(not(feature = "std"))]
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+#[cfg(feature = "std")]
+use std::time::Instant;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::UnicodeNormalization;
+
+#[cfg(feature = "std")]
+pub type DefaultHashBuilder = RandomState;
+/// Without `std` children are kept in a `BTreeMap` and the hasher is unused.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultHashBuilder {}
+
+/// The hasher used by the children maps, see [`WeightedTrie::with_hasher`].
+#[cfg(feature = "std")]
+pub trait ChildHasher: BuildHasher + Clone {}
+#[cfg(feature = "std")]
+impl<S: BuildHasher + Clone> ChildHasher for S {}
+/// The hasher used by the children maps, see [`WeightedTrie::with_hasher`].
+#[cfg(not(feature = "std"))]
+pub trait ChildHasher: Clone {}
+#[cfg(not(feature = "std"))]
+impl<S: Clone> ChildHasher for S {}
+
+/// Index of a node in the arena of its [`WeightedTrie`].
+type NodeId = u32;
+
+/// The root is always the first node of the arena.
+const ROOT: NodeId = 0;
+
+#[cfg(feature = "std")]
+type Children<S> = HashMap<char, NodeId, S>;
+#[cfg(not(feature = "std"))]
+type Children = BTreeMap<char, NodeId>;
+
+type SuggestionOrder = Arc<dyn Fn(&(i32, String), &(i32, String)) -> Ordering + Send + Sync>;
+type MergeFn = Arc<dyn Fn(i32, i32) -> i32 + Send + Sync>;
 
-pub struct TrieNode {
-    pub children: HashMap<char, Box<TrieNode>>,
-    pub suggestions: Vec<(i32, String)>,
+/// Separates the namespace from the word in [`WeightedTrie::insert_ns`].
+const NS_SEPARATOR: char = '\u{1f}';
+
+#[cfg(feature = "cache")]
+const SEARCH_CACHE_CAPACITY: usize = 256;
+
+/// Results of [`WeightedTrie::search_cached`], valid while `generation`
+/// matches the trie's.
+#[cfg(feature = "cache")]
+#[derive(Default)]
+struct SearchCache {
+    generation: u64,
+    tick: u64,
+    // prefix -> (last used tick, k, results)
+    entries: HashMap<String, (u64, usize, Vec<String>)>,
+}
+
+/// The `k` [`WeightedTrie::popular`] was last computed for and its result.
+#[cfg(feature = "std")]
+type PopularCache = Option<(usize, Vec<(String, i32)>)>;
+
+/// A node in the arena of a [`WeightedTrie`]. Children are arena indices, so
+/// a step down the trie is a map lookup and an index into one `Vec` rather
+/// than a pointer to its own allocation.
+struct Node<S> {
+    #[cfg(feature = "std")]
+    children: Children<S>,
+    #[cfg(not(feature = "std"))]
+    children: Children,
+    suggestions: Vec<(i32, String)>,
+    // whether a stored word ends here, as opposed to only passing through
+    terminal: bool,
+    #[cfg(not(feature = "std"))]
+    hasher: PhantomData<S>,
 }
 
-impl TrieNode {
-    pub fn new() -> TrieNode {
-        TrieNode {
-            children: HashMap::new(),
+impl<S: ChildHasher> Node<S> {
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn with_hasher(hasher: S) -> Node<S> {
+        Node {
+            #[cfg(feature = "std")]
+            children: HashMap::with_hasher(hasher),
+            #[cfg(not(feature = "std"))]
+            children: BTreeMap::new(),
+            suggestions: Vec::new(),
+            terminal: false,
+            #[cfg(not(feature = "std"))]
+            hasher: PhantomData,
+        }
+    }
+
+    fn empty_sibling(&self) -> Node<S> {
+        #[cfg(feature = "std")]
+        return Node::with_hasher(self.children.hasher().clone());
+        #[cfg(not(feature = "std"))]
+        Node {
+            children: BTreeMap::new(),
             suggestions: Vec::new(),
+            terminal: false,
+            hasher: PhantomData,
         }
     }
 }
 
-pub struct WeightedTrie {
-    root: TrieNode,
+/// The child of `id` at `c`, added to the end of the arena if missing.
+fn child_or_insert<S: ChildHasher>(nodes: &mut Vec<Node<S>>, id: NodeId, c: char) -> NodeId {
+    if let Some(&child) = nodes[id as usize].children.get(&c) {
+        return child;
+    }
+    let child = nodes.len() as NodeId;
+    let empty = nodes[id as usize].empty_sibling();
+    nodes.push(empty);
+    nodes[id as usize].children.insert(c, child);
+    child
 }
 
-pub struct WeightedString {
-    pub word: String,
-    pub weight: i32,
+/// A node of a [`WeightedTrie`], borrowed from the trie.
+pub struct TrieNode<'a, S = DefaultHashBuilder> {
+    nodes: &'a [Node<S>],
+    id: NodeId,
 }
 
-impl WeightedTrie {
-    pub fn new() -> WeightedTrie {
-        WeightedTrie {
-            root: TrieNode::new(),
+impl<S> Clone for TrieNode<'_, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for TrieNode<'_, S> {}
+
+impl<'a, S> TrieNode<'a, S> {
+    fn new(nodes: &'a [Node<S>], id: NodeId) -> TrieNode<'a, S> {
+        TrieNode { nodes, id }
+    }
+
+    fn node(self) -> &'a Node<S> {
+        &self.nodes[self.id as usize]
+    }
+
+    pub fn children(self) -> impl Iterator<Item = (char, TrieNode<'a, S>)> {
+        let nodes = self.nodes;
+        self.node()
+            .children
+            .iter()
+            .map(move |(&c, &child)| (c, TrieNode::new(nodes, child)))
+    }
+
+    /// Whether a stored word ends at this node rather than only passing
+    /// through it, so for "pizza" the node of "pi" is not terminal.
+    pub fn is_terminal(self) -> bool {
+        self.node().terminal
+    }
+
+    pub fn suggestions(self) -> &'a [(i32, String)] {
+        &self.node().suggestions
+    }
+
+    fn child_count(self) -> usize {
+        self.node().children.len()
+    }
+
+    fn is_root(self) -> bool {
+        self.id == ROOT
+    }
+}
+
+impl<'a, S: ChildHasher> TrieNode<'a, S> {
+    pub fn child(self, c: char) -> Option<TrieNode<'a, S>> {
+        let child = *self.node().children.get(&c)?;
+        Some(TrieNode::new(self.nodes, child))
+    }
+}
+
+impl<S> fmt::Debug for TrieNode<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrieNode")
+            .field("children", &DebugChildren(*self))
+            .field("suggestions", &self.suggestions())
+            .field("terminal", &self.is_terminal())
+            .finish()
+    }
+}
+
+struct DebugChildren<'a, S>(TrieNode<'a, S>);
+
+impl<S> fmt::Debug for DebugChildren<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.0.children()).finish()
+    }
+}
+
+impl<S: ChildHasher> PartialEq for TrieNode<'_, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.suggestions() == other.suggestions()
+            && self.is_terminal() == other.is_terminal()
+            && self.child_count() == other.child_count()
+            && self
+                .children()
+                .all(|(c, child)| other.child(c) == Some(child))
+    }
+}
+
+/// Mutable access to a node of a [`WeightedTrie`], see
+/// [`WeightedTrie::root_mut`].
+pub struct TrieNodeMut<'a, S = DefaultHashBuilder> {
+    nodes: &'a mut [Node<S>],
+    id: NodeId,
+}
+
+impl<'a, S: ChildHasher> TrieNodeMut<'a, S> {
+    /// Mutable access to the child at `c`. Nothing keeps the ancestors'
+    /// suggestions in sync with changes made below it.
+    pub fn child_mut(self, c: char) -> Option<TrieNodeMut<'a, S>> {
+        let id = *self.nodes[self.id as usize].children.get(&c)?;
+        Some(TrieNodeMut {
+            nodes: self.nodes,
+            id,
+        })
+    }
+
+    /// Mutable access to the suggestions, which `search` expects to be sorted.
+    /// Call [`WeightedTrie::rebuild_suggestions`] after editing them.
+    pub fn suggestions_raw_mut(&mut self) -> &mut Vec<(i32, String)> {
+        &mut self.nodes[self.id as usize].suggestions
+    }
+}
+
+impl<S> fmt::Debug for TrieNodeMut<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        TrieNode::new(self.nodes, self.id).fmt(f)
+    }
+}
+
+pub struct WeightedTrie<S = DefaultHashBuilder> {
+    // every node, the root first, see `Node`
+    nodes: Vec<Node<S>>,
+    order: SuggestionOrder,
+    // new and reweighted entries go before their equals, see `TieBreak::RecencyFirst`
+    recency_first: bool,
+    trim: bool,
+    collapse_whitespace: bool,
+    fold_case: bool,
+    #[cfg(feature = "unicode-normalization")]
+    nfc: bool,
+    max_word_len: Option<usize>,
+    max_children: Option<usize>,
+    min_query_len: usize,
+    max_suggestions: Option<usize>,
+    depth_scaled_cap: Option<usize>,
+    memory_budget: Option<usize>,
+    // running estimate, only kept while there is a budget
+    estimated_bytes: usize,
+    dedup: Option<DedupPolicy>,
+    merge_fn: Option<MergeFn>,
+    selection_increment: i32,
+    // only kept with `with_metrics`, atomic since `search` takes `&self`
+    metrics: Option<QueryCounters>,
+    // dedup key -> the spelling that was stored first
+    dedup_keys: BTreeMap<String, String>,
+    // stored word -> its key, for words inserted under a key of their own
+    display_keys: BTreeMap<String, String>,
+    sub_weights: BTreeMap<String, Vec<i32>>,
+    ids: BTreeMap<String, u32>,
+    categories: BTreeMap<String, u16>,
+    // stored word -> sequence number of its last insert or reweight, only
+    // kept for `TieBreak::RecencyFirst`
+    last_touched: BTreeMap<String, u64>,
+    next_seq: u64,
+    #[cfg(feature = "std")]
+    inserted_at: HashMap<String, Instant>,
+    // bumped by every mutation, see `invalidate`
+    #[cfg(feature = "cache")]
+    generation: u64,
+    #[cfg(feature = "cache")]
+    cache: std::sync::Mutex<SearchCache>,
+    // cleared by `invalidate`
+    #[cfg(feature = "std")]
+    popular: std::sync::Mutex<PopularCache>,
+}
+
+impl<S> fmt::Debug for WeightedTrie<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeightedTrie")
+            .field("root", &TrieNode::new(&self.nodes, ROOT))
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: ChildHasher> PartialEq for WeightedTrie<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.root() == other.root()
+    }
+}
+
+impl<S: ChildHasher> Extend<WeightedString> for WeightedTrie<S> {
+    fn extend<I: IntoIterator<Item = WeightedString>>(&mut self, iter: I) {
+        iter.into_iter()
+            .for_each(|ws| self.insert(ws.word, ws.weight));
+    }
+}
+
+impl Default for WeightedTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Prefix search shared by the trie representations, for code that should
+/// work with any of them.
+///
+/// ```
+/// use weighted_trie::{PrefixSearch, WeightedTrie};
+///
+/// fn best(index: &impl PrefixSearch, prefix: &str) -> Option<String> {
+///     index.search_top_k(prefix, 1).pop().map(|(word, _)| word)
+/// }
+///
+/// let mut trie = WeightedTrie::new();
+/// trie.insert("pizza".to_owned(), 10);
+/// assert_eq!(best(&trie, "pi"), Some("pizza".to_owned()));
+/// assert_eq!(best(&trie.freeze(), "pi"), Some("pizza".to_owned()));
+/// ```
+pub trait PrefixSearch {
+    /// The `k` best completions of `prefix` with their weights, best first.
+    fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)>;
+}
+
+impl<S: ChildHasher> PrefixSearch for WeightedTrie<S> {
+    fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)> {
+        WeightedTrie::search_top_k(self, prefix, k)
+    }
+}
+
+/// Configures a [`WeightedTrie`] with chainable setters.
+///
+/// ```
+/// use weighted_trie::{DedupPolicy, WeightedTrie};
+///
+/// let mut trie = WeightedTrie::builder()
+///     .trim(true)
+///     .max_suggestions_per_node(50)
+///     .dedup_policy(DedupPolicy::CaseInsensitive)
+///     .build();
+/// trie.insert(" pizza ".to_owned(), 1);
+/// assert_eq!(trie.search("pi"), vec!["pizza"]);
+/// ```
+pub struct WeightedTrieBuilder<S = DefaultHashBuilder> {
+    hasher: S,
+    order: Option<SuggestionOrder>,
+    recency_first: bool,
+    trim: bool,
+    collapse_whitespace: bool,
+    case_insensitive: bool,
+    #[cfg(feature = "unicode-normalization")]
+    nfc: bool,
+    max_word_len: Option<usize>,
+    max_children: Option<usize>,
+    min_query_len: usize,
+    max_suggestions: Option<usize>,
+    depth_scaled_cap: Option<usize>,
+    memory_budget: Option<usize>,
+    dedup: Option<DedupPolicy>,
+    merge_fn: Option<MergeFn>,
+    selection_increment: i32,
+    metrics: bool,
+}
+
+impl WeightedTrieBuilder {
+    pub fn new() -> WeightedTrieBuilder {
+        WeightedTrieBuilder {
+            hasher: DefaultHashBuilder::default(),
+            order: None,
+            recency_first: false,
+            trim: false,
+            collapse_whitespace: false,
+            case_insensitive: false,
+            #[cfg(feature = "unicode-normalization")]
+            nfc: false,
+            max_word_len: None,
+            max_children: None,
+            min_query_len: 0,
+            max_suggestions: None,
+            depth_scaled_cap: None,
+            memory_budget: None,
+            dedup: None,
+            merge_fn: None,
+            selection_increment: 1,
+            metrics: false,
         }
     }
+}
 
-    pub fn build(weighted_strings: Vec<WeightedString>) -> WeightedTrie {
-        let mut trie = WeightedTrie::new();
-        weighted_strings
-            .into_iter()
-            .for_each(|ws| trie.insert(ws.word, ws.weight));
+impl Default for WeightedTrieBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: ChildHasher> WeightedTrieBuilder<S> {
+    /// See [`WeightedTrie::with_order`].
+    pub fn order(
+        mut self,
+        cmp: impl Fn(&(i32, String), &(i32, String)) -> Ordering + Send + Sync + 'static,
+    ) -> Self {
+        self.order = Some(Arc::new(cmp));
+        self.recency_first = false;
+        self
+    }
+
+    /// See [`WeightedTrie::with_tie_break`]. Replaces any earlier
+    /// [`WeightedTrieBuilder::order`].
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.order = Some(tie_break.order());
+        self.recency_first = tie_break == TieBreak::RecencyFirst;
+        self
+    }
+
+    /// See [`WeightedTrie::with_trim`].
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// See [`WeightedTrie::with_collapsed_whitespace`].
+    pub fn collapse_whitespace(mut self, collapse: bool) -> Self {
+        self.collapse_whitespace = collapse;
+        self
+    }
+
+    /// See [`WeightedTrie::with_case_insensitive`].
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// See [`WeightedTrie::with_nfc`].
+    #[cfg(feature = "unicode-normalization")]
+    pub fn nfc(mut self, nfc: bool) -> Self {
+        self.nfc = nfc;
+        self
+    }
+
+    /// See [`WeightedTrie::with_max_word_len`].
+    pub fn max_word_len(mut self, max: usize) -> Self {
+        self.max_word_len = Some(max);
+        self
+    }
+
+    /// See [`WeightedTrie::with_max_children_per_node`].
+    pub fn max_children_per_node(mut self, max: usize) -> Self {
+        self.max_children = Some(max);
+        self
+    }
+
+    /// See [`WeightedTrie::with_min_query_len`].
+    pub fn min_query_len(mut self, min: usize) -> Self {
+        self.min_query_len = min;
+        self
+    }
+
+    /// See [`WeightedTrie::with_max_suggestions_per_node`].
+    pub fn max_suggestions_per_node(mut self, cap: usize) -> Self {
+        self.max_suggestions = Some(cap);
+        self
+    }
+
+    /// See [`WeightedTrie::with_depth_scaled_cap`].
+    pub fn depth_scaled_cap(mut self, base: usize) -> Self {
+        self.depth_scaled_cap = Some(base);
+        self
+    }
+
+    /// See [`WeightedTrie::with_memory_budget`].
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// See [`WeightedTrie::with_dedup_policy`].
+    pub fn dedup_policy(mut self, policy: DedupPolicy) -> Self {
+        self.dedup = Some(policy);
+        self
+    }
+
+    /// See [`WeightedTrie::with_merge_fn`].
+    pub fn merge_fn(mut self, f: impl Fn(i32, i32) -> i32 + Send + Sync + 'static) -> Self {
+        self.merge_fn = Some(Arc::new(f));
+        self
+    }
+
+    /// See [`WeightedTrie::with_selection_increment`].
+    pub fn selection_increment(mut self, increment: i32) -> Self {
+        self.selection_increment = increment;
+        self
+    }
+
+    /// See [`WeightedTrie::with_metrics`].
+    pub fn metrics(mut self, metrics: bool) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// See [`WeightedTrie::with_hasher`].
+    pub fn hasher<H: ChildHasher>(self, hasher: H) -> WeightedTrieBuilder<H> {
+        WeightedTrieBuilder {
+            hasher,
+            order: self.order,
+            recency_first: self.recency_first,
+            trim: self.trim,
+            collapse_whitespace: self.collapse_whitespace,
+            case_insensitive: self.case_insensitive,
+            #[cfg(feature = "unicode-normalization")]
+            nfc: self.nfc,
+            max_word_len: self.max_word_len,
+            max_children: self.max_children,
+            min_query_len: self.min_query_len,
+            max_suggestions: self.max_suggestions,
+            depth_scaled_cap: self.depth_scaled_cap,
+            memory_budget: self.memory_budget,
+            dedup: self.dedup,
+            merge_fn: self.merge_fn,
+            selection_increment: self.selection_increment,
+            metrics: self.metrics,
+        }
+    }
+
+    pub fn build(self) -> WeightedTrie<S> {
+        let mut trie = WeightedTrie::with_hasher(self.hasher);
+        if let Some(order) = self.order {
+            trie.order = order;
+        }
+        trie.recency_first = self.recency_first;
+        trie.trim = self.trim;
+        trie.collapse_whitespace = self.collapse_whitespace;
+        trie.fold_case = self.case_insensitive;
+        #[cfg(feature = "unicode-normalization")]
+        {
+            trie.nfc = self.nfc;
+        }
+        trie.max_word_len = self.max_word_len;
+        trie.max_children = self.max_children;
+        trie.min_query_len = self.min_query_len;
+        trie.max_suggestions = self.max_suggestions;
+        trie.depth_scaled_cap = self.depth_scaled_cap;
+        trie.memory_budget = self.memory_budget;
+        trie.dedup = self.dedup;
+        trie.merge_fn = self.merge_fn;
+        trie.selection_increment = self.selection_increment;
+        if self.metrics {
+            trie.metrics = Some(QueryCounters::default());
+        }
         trie
     }
+}
+
+impl<S> fmt::Debug for WeightedTrieBuilder<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeightedTrieBuilder")
+            .field("recency_first", &self.recency_first)
+            .field("trim", &self.trim)
+            .field("collapse_whitespace", &self.collapse_whitespace)
+            .field("case_insensitive", &self.case_insensitive)
+            .field("max_word_len", &self.max_word_len)
+            .field("max_children", &self.max_children)
+            .field("min_query_len", &self.min_query_len)
+            .field("max_suggestions", &self.max_suggestions)
+            .field("depth_scaled_cap", &self.depth_scaled_cap)
+            .field("memory_budget", &self.memory_budget)
+            .field("dedup", &self.dedup)
+            .field("merge_fn", &self.merge_fn.is_some())
+            .field("selection_increment", &self.selection_increment)
+            .field("metrics", &self.metrics)
+            .finish_non_exhaustive()
+    }
+}
 
-    pub fn insert(&mut self, word: String, weight: i32) {
-        let mut node = &mut self.root;
-        for c in word.chars() {
-            node = node
-                .children
-                .entry(c)
-                .or_insert_with(|| Box::new(TrieNode::new()));
-            let pos = node
-                .suggestions
-                .binary_search_by_key(&Reverse(weight), |&(w, _)| Reverse(w))
-                .unwrap_or_else(|x| x);
-            node.suggestions.insert(pos, (weight, word.clone()));
+/// Builds a [`WeightedTrie`] a chunk at a time, so a large dataset can be
+/// loaded from an async task that yields between chunks instead of blocking
+/// the runtime for the whole build.
+///
+/// ```
+/// use weighted_trie::{ChunkedBuilder, WeightedString};
+///
+/// let words: Vec<WeightedString> = (0..100)
+///     .map(|i| WeightedString { word: format!("word{i}"), weight: i })
+///     .collect();
+/// let mut builder = ChunkedBuilder::new();
+/// for chunk in words.chunks(32) {
+///     builder.feed(chunk);
+///     // yield to the runtime here
+/// }
+/// let trie = builder.finish();
+/// assert_eq!(trie.search("word9")[0], "word99");
+/// ```
+#[derive(Debug)]
+pub struct ChunkedBuilder<S = DefaultHashBuilder> {
+    trie: WeightedTrie<S>,
+}
+
+impl ChunkedBuilder {
+    pub fn new() -> ChunkedBuilder {
+        ChunkedBuilder::from_trie(WeightedTrie::new())
+    }
+}
+
+impl Default for ChunkedBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: ChildHasher> ChunkedBuilder<S> {
+    /// Continues building into `trie`, keeping its configuration and words.
+    pub fn from_trie(trie: WeightedTrie<S>) -> ChunkedBuilder<S> {
+        ChunkedBuilder { trie }
+    }
+
+    /// Inserts every word of `chunk`, in order.
+    pub fn feed(&mut self, chunk: &[WeightedString]) {
+        for ws in chunk {
+            self.trie.insert(ws.word.clone(), ws.weight);
         }
     }
 
-    pub fn search(&self, prefix: &str) -> Vec<String> {
-        let mut node = &self.root;
-        for c in prefix.chars() {
-            if let Some(child) = node.children.get(&c) {
-                node = child;
-            } else {
-                return vec![];
+    pub fn finish(self) -> WeightedTrie<S> {
+        self.trie
+    }
+}
+
+/// A borrowed view of the words under a prefix, see [`WeightedTrie::subtrie`].
+pub struct SubTrie<'a, S = DefaultHashBuilder> {
+    trie: &'a WeightedTrie<S>,
+    node: TrieNode<'a, S>,
+}
+
+impl<S: ChildHasher> SubTrie<'_, S> {
+    /// Same as searching the full trie for the view's prefix followed by
+    /// `rel_prefix`, without walking the shared part again.
+    pub fn search(&self, rel_prefix: &str) -> Vec<String> {
+        let mut node = self.node;
+        for c in self.trie.key(rel_prefix).chars() {
+            match node.child(c) {
+                Some(child) => node = child,
+                None => return vec![],
             }
         }
-
-        node.suggestions
+        node.suggestions()
             .iter()
             .map(|(_, word)| word.clone())
             .collect()
     }
 }
+
+impl<S> fmt::Debug for SubTrie<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubTrie")
+            .field("node", &self.node)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How [`WeightedTrie::merge_with`] resolves a word present in both tries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePolicy {
+    Sum,
+    Max,
+    /// Keep the weight of the trie being merged into.
+    KeepLeft,
+    /// Take the weight of the trie being merged in.
+    KeepRight,
+}
+
+/// How [`WeightedTrie::with_tie_break`] orders completions of equal weight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Earlier inserted first, the default.
+    #[default]
+    InsertionOrder,
+    /// Fewer chars first, then lexicographic, since a shorter completion is
+    /// closer to what was typed.
+    ShorterFirst,
+    /// Most recently inserted or updated first.
+    ///
+    /// Every insert and reweight gives the word a new sequence number, so a
+    /// new entry goes before its equals, a reweighted one moves in front of
+    /// them and [`WeightedTrie::rebuild_suggestions`] restores the same order.
+    RecencyFirst,
+    /// By [`stable_id`] of the word, so equal weights come out in the same
+    /// order whatever the insertion order, as paginating across replicas
+    /// needs.
+    StableId,
+}
+
+impl TieBreak {
+    fn order(self) -> SuggestionOrder {
+        match self {
+            TieBreak::InsertionOrder | TieBreak::RecencyFirst => Arc::new(|a, b| b.0.cmp(&a.0)),
+            TieBreak::StableId => Arc::new(|a, b| {
+                b.0.cmp(&a.0)
+                    .then_with(|| stable_id(&a.1).cmp(&stable_id(&b.1)))
+                    .then_with(|| a.1.cmp(&b.1))
+            }),
+            TieBreak::ShorterFirst => Arc::new(|a, b| {
+                b.0.cmp(&a.0)
+                    .then_with(|| word_char_len(&a.1).cmp(&word_char_len(&b.1)))
+                    .then_with(|| a.1.cmp(&b.1))
+            }),
+        }
+    }
+}
+
+/// Line order of [`WeightedTrie::write_tsv`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TsvOrder {
+    /// Heaviest first, equal weights by word.
+    Weight,
+    /// Lexicographic by word.
+    Word,
+}
+
+/// What counts as the same word for [`WeightedTrie::with_dedup_policy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DedupPolicy {
+    /// Only identical strings are merged.
+    Exact,
+    /// Strings equal after lowercasing are merged.
+    CaseInsensitive,
+    /// Strings equal after lowercasing and stripping surrounding punctuation
+    /// are merged.
+    Normalized,
+}
+
+impl DedupPolicy {
+    fn key(self, word: &str) -> String {
+        match self {
+            DedupPolicy::Exact => word.to_owned(),
+            DedupPolicy::CaseInsensitive => word.to_lowercase(),
+            DedupPolicy::Normalized => word
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedString {
+    pub word: String,
+    pub weight: i32,
+}
+
+/// Everything stored about a word, see [`WeightedTrie::get`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WordEntry {
+    pub weight: i32,
+    /// Set by [`WeightedTrie::insert_with_id`].
+    pub id: Option<u32>,
+    /// Set by [`WeightedTrie::insert_categorized`].
+    pub category: Option<u16>,
+}
+
+/// A broken invariant found by [`WeightedTrie::validate`], located by the
+/// prefix of the node it was found at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationIssue {
+    DuplicateWord {
+        prefix: String,
+        word: String,
+    },
+    /// `suggestions[index]` should come after `suggestions[index + 1]`.
+    OutOfOrder {
+        prefix: String,
+        index: usize,
+    },
+    /// `word` is stored below `prefix` but not in the node at `prefix`.
+    MissingFromAncestor {
+        prefix: String,
+        word: String,
+    },
+}
+
+/// How much of the trie a DAWG could share, see
+/// [`WeightedTrie::duplicate_subtree_stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DawgPotential {
+    /// Subtrees rooted below the root, one per node.
+    pub subtrees: usize,
+    /// Subtrees that differ in shape, counting where words end but not weights.
+    pub distinct_subtrees: usize,
+}
+
+impl DawgPotential {
+    /// Subtrees a DAWG could replace with a shared copy.
+    pub fn duplicates(&self) -> usize {
+        self.subtrees - self.distinct_subtrees
+    }
+}
+
+/// Data quality counts of [`WeightedTrie::build_reporting`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BuildReport {
+    /// Every input, valid or not.
+    pub inputs: usize,
+    /// Words stored, each once.
+    pub distinct: usize,
+    /// Repeats of an already stored word, merged into it.
+    pub duplicates: usize,
+    /// Inputs [`WeightedTrie::try_insert`] rejects, with the reason.
+    pub rejected: Vec<(WeightedString, TrieError)>,
+}
+
+/// Query counters of [`WeightedTrie::metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryMetrics {
+    /// Queries served.
+    pub queries: u64,
+    /// Queries that returned no completions.
+    pub zero_results: u64,
+    /// Completions returned over all queries.
+    pub results: u64,
+}
+
+impl QueryMetrics {
+    /// Mean number of completions per query, 0 before the first one.
+    pub fn average_results(&self) -> f64 {
+        if self.queries == 0 {
+            return 0.0;
+        }
+        self.results as f64 / self.queries as f64
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueryCounters {
+    queries: AtomicU64,
+    zero_results: AtomicU64,
+    results: AtomicU64,
+}
+
+impl QueryCounters {
+    fn record(&self, results: usize) {
+        self.queries.fetch_add(1, AtomicOrdering::Relaxed);
+        if results == 0 {
+            self.zero_results.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+        self.results
+            .fetch_add(results as u64, AtomicOrdering::Relaxed);
+    }
+
+    fn snapshot(&self) -> QueryMetrics {
+        QueryMetrics {
+            queries: self.queries.load(AtomicOrdering::Relaxed),
+            zero_results: self.zero_results.load(AtomicOrdering::Relaxed),
+            results: self.results.load(AtomicOrdering::Relaxed),
+        }
+    }
+}
+
+/// Ranks of intended words for offline evaluation, see
+/// [`WeightedTrie::evaluate`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EvalReport {
+    /// Zero-based rank of each case's intended word among the completions of
+    /// its prefix, `None` when it isn't one.
+    pub ranks: Vec<Option<usize>>,
+}
+
+impl EvalReport {
+    /// Mean of `1 / (rank + 1)` over all cases, counting misses as 0.
+    pub fn mean_reciprocal_rank(&self) -> f64 {
+        if self.ranks.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .ranks
+            .iter()
+            .flatten()
+            .map(|&rank| 1.0 / (rank + 1) as f64)
+            .sum();
+        sum / self.ranks.len() as f64
+    }
+
+    /// Share of cases whose intended word is among the first `k` completions.
+    pub fn hit_at(&self, k: usize) -> f64 {
+        if self.ranks.is_empty() {
+            return 0.0;
+        }
+        let hits = self
+            .ranks
+            .iter()
+            .flatten()
+            .filter(|&&rank| rank < k)
+            .count();
+        hits as f64 / self.ranks.len() as f64
+    }
+}
+
+/// Word-level changes between two tries, see [`WeightedTrie::diff`]. Each
+/// list is sorted by word.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrieDiff<'a> {
+    /// Words only in the other trie, with their weight there.
+    pub added: Vec<(&'a str, i32)>,
+    /// Words only in this trie, with their weight here.
+    pub removed: Vec<(&'a str, i32)>,
+    /// Words in both with a different weight, as `(word, old, new)`.
+    pub reweighted: Vec<(&'a str, i32, i32)>,
+}
+
+impl TrieDiff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.reweighted.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DepthStat {
+    pub depth: usize,
+    pub nodes: usize,
+    pub suggestions: usize,
+    /// Heap bytes of the suggestion vectors and their strings, by capacity.
+    pub suggestion_bytes: usize,
+}
+
+impl WeightedTrie {
+    pub fn new() -> WeightedTrie {
+        WeightedTrie::with_hasher(DefaultHashBuilder::default())
+    }
+
+    pub fn builder() -> WeightedTrieBuilder {
+        WeightedTrieBuilder::new()
+    }
+
+    /// Creates a trie ranking by weight descending and `tie_break` among
+    /// equal weights.
+    pub fn with_tie_break(tie_break: TieBreak) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.order = tie_break.order();
+        trie.recency_first = tie_break == TieBreak::RecencyFirst;
+        trie
+    }
+
+    pub fn with_order(
+        cmp: impl Fn(&(i32, String), &(i32, String)) -> Ordering + Send + Sync + 'static,
+    ) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.order = Arc::new(cmp);
+        trie
+    }
+
+    /// Creates a trie where inserting a word that is already stored replaces
+    /// its weight with `f(old, new)` instead of storing it again.
+    ///
+    /// ```
+    /// use weighted_trie::WeightedTrie;
+    ///
+    /// let mut trie = WeightedTrie::with_merge_fn(i32::max);
+    /// trie.insert("pie".to_owned(), 5);
+    /// trie.insert("pie".to_owned(), 2);
+    /// assert_eq!(trie.search_top_k("pie", 2), vec![("pie".to_owned(), 5)]);
+    /// ```
+    pub fn with_merge_fn(f: impl Fn(i32, i32) -> i32 + Send + Sync + 'static) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.merge_fn = Some(Arc::new(f));
+        trie
+    }
+
+    /// Creates a trie where [`WeightedTrie::record_selection`] adds
+    /// `increment` to the selected word's weight instead of 1.
+    pub fn with_selection_increment(increment: i32) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.selection_increment = increment;
+        trie
+    }
+
+    /// Creates a trie that counts the queries [`WeightedTrie::search`] serves,
+    /// for [`WeightedTrie::metrics`].
+    pub fn with_metrics() -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.metrics = Some(QueryCounters::default());
+        trie
+    }
+
+    /// Creates a trie that trims surrounding whitespace from inserted words and
+    /// from queried prefixes.
+    pub fn with_trim() -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.trim = true;
+        trie
+    }
+
+    /// Creates a trie that collapses every run of whitespace inside inserted
+    /// words and queried prefixes to a single space, so `"new  york"` and
+    /// `"new york"` are the same phrase. Unlike [`WeightedTrie::with_trim`]
+    /// the edges are left alone.
+    ///
+    /// Words are stored collapsed; [`WeightedTrie::insert_keyed`] keeps the
+    /// original spelling for display.
+    pub fn with_collapsed_whitespace() -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.collapse_whitespace = true;
+        trie
+    }
+
+    /// Creates a trie that matches prefixes case-insensitively while returning
+    /// words as they were inserted.
+    ///
+    /// Words are lowercased as a whole before being keyed, since lowercasing
+    /// can change the number of chars (`'İ'` becomes `"i̇"`).
+    pub fn with_case_insensitive() -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.fold_case = true;
+        trie
+    }
+
+    pub fn build(weighted_strings: Vec<WeightedString>) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        weighted_strings
+            .into_iter()
+            .for_each(|ws| trie.insert(ws.word, ws.weight));
+        trie
+    }
+
+    /// Builds a trie from raw pairs, summing the weights of repeated words.
+    ///
+    /// Each word is inserted once, at the position of its first occurrence.
+    pub fn build_aggregated(pairs: Vec<(String, i32)>) -> WeightedTrie {
+        let mut index: BTreeMap<String, usize> = BTreeMap::new();
+        let mut totals: Vec<(String, i32)> = Vec::new();
+        for (word, weight) in pairs {
+            match index.get(&word) {
+                Some(&i) => totals[i].1 = totals[i].1.saturating_add(weight),
+                None => {
+                    index.insert(word.clone(), totals.len());
+                    totals.push((word, weight));
+                }
+            }
+        }
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in totals {
+            trie.insert(word, weight);
+        }
+        trie
+    }
+
+    /// Builds a trie from a word to weight map.
+    ///
+    /// Map iteration order is arbitrary, so words of equal weight are inserted
+    /// in lexicographic order to keep the result deterministic.
+    #[cfg(feature = "std")]
+    pub fn from_map(map: HashMap<String, i32>) -> WeightedTrie {
+        let mut pairs: Vec<(String, i32)> = map.into_iter().collect();
+        pairs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in pairs {
+            trie.insert(word, weight);
+        }
+        trie
+    }
+
+    /// Creates a trie that brings inserted words and queried prefixes into
+    /// Unicode NFC, so a decomposed `"e\u{301}"` and a precomposed `"é"` share
+    /// a path. Words are stored and returned in NFC.
+    ///
+    /// Methods taking a whole stored word, like
+    /// [`WeightedTrie::update_weights`], expect its NFC spelling.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn with_nfc() -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.nfc = true;
+        trie
+    }
+
+    /// Creates a trie that rejects words longer than `max` chars.
+    pub fn with_max_word_len(max: usize) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.max_word_len = Some(max);
+        trie
+    }
+
+    /// Creates a trie whose nodes have at most `max` children, bounding node
+    /// size on untrusted input. Inserts that would add one more are rejected
+    /// with [`TrieError::TooManyChildren`] and leave the trie unchanged.
+    pub fn with_max_children_per_node(max: usize) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.max_children = Some(max);
+        trie
+    }
+
+    /// Creates a trie whose [`WeightedTrie::search`], other `search_*`
+    /// methods and [`WeightedTrie::best`] return nothing for prefixes shorter
+    /// than `min` chars, without walking the trie, so autocomplete doesn't
+    /// fire on the first keystroke.
+    ///
+    /// [`WeightedTrie::search_wildcard`] matches whole patterns and lookups
+    /// like [`WeightedTrie::contains`] or [`WeightedTrie::max_weight`] aren't
+    /// searches, so those ignore it.
+    pub fn with_min_query_len(min: usize) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.min_query_len = min;
+        trie
+    }
+
+    /// Creates a trie that keeps at most `cap` suggestions per node.
+    ///
+    /// The cap is enforced on every insert, so memory stays bounded while the
+    /// trie is being built. Words that fall out of every node along their path
+    /// are no longer stored at all.
+    pub fn with_max_suggestions_per_node(cap: usize) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.max_suggestions = Some(cap);
+        trie
+    }
+
+    /// Creates a trie whose nodes `d` chars deep keep at most `base / d`
+    /// suggestions, and at least one.
+    ///
+    /// Short prefixes, which need the longest lists, keep the most, while deep
+    /// nodes with few useful completions stay small. Like
+    /// [`WeightedTrie::with_max_suggestions_per_node`] it is enforced on every
+    /// insert; combined with it the smaller cap applies.
+    pub fn with_depth_scaled_cap(base: usize) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.depth_scaled_cap = Some(base);
+        trie
+    }
+
+    /// Creates a trie that keeps [`WeightedTrie::estimated_memory_bytes`] within
+    /// `bytes` by evicting the lowest-weighted words after an insert.
+    ///
+    /// Each eviction walks the whole trie, so the budget suits tries that stay
+    /// small rather than bulk builds that overflow it repeatedly.
+    pub fn with_memory_budget(bytes: usize) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.memory_budget = Some(bytes);
+        trie
+    }
+
+    /// Creates a trie that merges words considered the same by `policy`.
+    ///
+    /// The spelling inserted first is kept and the weights of later variants
+    /// are added to it. Without a policy every insert is stored separately.
+    pub fn with_dedup_policy(policy: DedupPolicy) -> WeightedTrie {
+        let mut trie = WeightedTrie::new();
+        trie.dedup = Some(policy);
+        trie
+    }
+
+    /// Like [`WeightedTrie::build`], calling `callback` with the number of words
+    /// inserted so far after every `every` inserts and once more at the end.
+    pub fn build_with_progress(
+        weighted_strings: impl IntoIterator<Item = WeightedString>,
+        every: usize,
+        mut callback: impl FnMut(usize),
+    ) -> WeightedTrie {
+        let every = every.max(1);
+        let mut trie = WeightedTrie::new();
+        let mut count = 0;
+        for ws in weighted_strings {
+            trie.insert(ws.word, ws.weight);
+            count += 1;
+            if count % every == 0 {
+                callback(count);
+            }
+        }
+        if count % every != 0 || count == 0 {
+            callback(count);
+        }
+        trie
+    }
+
+    /// Builds a trie from strings that are already sorted by weight descending.
+    ///
+    /// Every suggestion is appended instead of binary searched, which is much
+    /// faster than [`WeightedTrie::build`]. Unsorted input is caught by a debug
+    /// assertion, in release builds it silently produces a misordered trie.
+    pub fn build_presorted(weighted_strings: Vec<WeightedString>) -> WeightedTrie {
+        debug_assert!(
+            weighted_strings
+                .windows(2)
+                .all(|pair| pair[0].weight >= pair[1].weight),
+            "build_presorted input must be sorted by weight descending"
+        );
+        let mut trie = WeightedTrie::new();
+        for ws in weighted_strings {
+            let mut node = ROOT;
+            for c in ws.word.chars() {
+                node = child_or_insert(&mut trie.nodes, node, c);
+                let entry = (ws.weight, ws.word.clone());
+                trie.nodes[node as usize].suggestions.push(entry);
+            }
+            trie.nodes[node as usize].terminal = !ws.word.is_empty();
+        }
+        trie
+    }
+
+    /// Builds a trie from `word\tweight` lines, the format of
+    /// [`WeightedTrie::write_tsv`], reading and inserting one line at a time
+    /// so the whole file is never held in memory.
+    ///
+    /// The weight is taken after the last tab, so words may contain tabs.
+    /// Empty lines are skipped and a line without a valid weight fails with
+    /// [`std::io::ErrorKind::InvalidData`].
+    #[cfg(feature = "std")]
+    pub fn from_lines_streaming(mut r: impl std::io::BufRead) -> std::io::Result<WeightedTrie> {
+        let mut trie = WeightedTrie::new();
+        let mut line = String::new();
+        while r.read_line(&mut line)? > 0 {
+            let record = line.trim_end_matches(['\n', '\r']);
+            if !record.is_empty() {
+                let (word, weight) = record
+                    .rsplit_once('\t')
+                    .and_then(|(word, weight)| Some((word, weight.parse().ok()?)))
+                    .ok_or_else(|| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("invalid weighted line {record:?}"),
+                        )
+                    })?;
+                trie.insert(word.to_owned(), weight);
+            }
+            line.clear();
+        }
+        Ok(trie)
+    }
+
+    /// Builds a trie from the valid inputs and returns the rejected ones with
+    /// the reason. Repeated words are rejected, the first occurrence is kept.
+    pub fn build_checked(
+        weighted_strings: Vec<WeightedString>,
+    ) -> (WeightedTrie, Vec<(WeightedString, TrieError)>) {
+        let mut trie = WeightedTrie::new();
+        let rejected = trie.extend_checked(weighted_strings);
+        (trie, rejected)
+    }
+
+    /// Builds a trie from the valid inputs, summing the weights of repeated
+    /// words into one entry, and reports what was collapsed or rejected.
+    pub fn build_reporting(weighted_strings: Vec<WeightedString>) -> (WeightedTrie, BuildReport) {
+        let mut trie = WeightedTrie::new();
+        let mut report = BuildReport {
+            inputs: weighted_strings.len(),
+            ..BuildReport::default()
+        };
+        let mut repeated = Vec::new();
+        for ws in weighted_strings {
+            if let Err(err) = trie.check_word(&ws.word) {
+                report.rejected.push((ws, err));
+            } else if trie.find_weight(&ws.word).is_some() {
+                repeated.push((ws.word, ws.weight));
+            } else {
+                trie.insert(ws.word, ws.weight);
+                report.distinct += 1;
+            }
+        }
+        report.duplicates = repeated.len();
+        let repeated: Vec<(&str, i32)> = repeated
+            .iter()
+            .map(|(word, weight)| (word.as_str(), *weight))
+            .collect();
+        trie.update_weights(&repeated);
+        (trie, report)
+    }
+}
+
+impl<S: ChildHasher> WeightedTrie<S> {
+    /// Creates a trie whose children maps use `hasher`.
+    ///
+    /// Keys are single chars, so a fast hasher without DoS resistance is
+    /// usually a good fit.
+    pub fn with_hasher(hasher: S) -> WeightedTrie<S> {
+        WeightedTrie {
+            nodes: vec![Node::with_hasher(hasher)],
+            order: TieBreak::InsertionOrder.order(),
+            recency_first: false,
+            trim: false,
+            collapse_whitespace: false,
+            fold_case: false,
+            #[cfg(feature = "unicode-normalization")]
+            nfc: false,
+            max_word_len: None,
+            max_children: None,
+            min_query_len: 0,
+            max_suggestions: None,
+            depth_scaled_cap: None,
+            memory_budget: None,
+            estimated_bytes: 0,
+            dedup: None,
+            merge_fn: None,
+            selection_increment: 1,
+            metrics: None,
+            dedup_keys: BTreeMap::new(),
+            display_keys: BTreeMap::new(),
+            sub_weights: BTreeMap::new(),
+            ids: BTreeMap::new(),
+            categories: BTreeMap::new(),
+            last_touched: BTreeMap::new(),
+            next_seq: 0,
+            #[cfg(feature = "std")]
+            inserted_at: HashMap::new(),
+            #[cfg(feature = "cache")]
+            generation: 0,
+            #[cfg(feature = "cache")]
+            cache: Default::default(),
+            #[cfg(feature = "std")]
+            popular: Default::default(),
+        }
+    }
+
+    /// Inserts `word`, silently skipping it if [`WeightedTrie::try_insert`]
+    /// would reject it.
+    pub fn insert(&mut self, word: String, weight: i32) {
+        let _ = self.try_insert(word, weight);
+    }
+
+    pub fn try_insert(&mut self, word: String, weight: i32) -> Result<(), TrieError> {
+        self.insert_stored(word, weight).map(drop)
+    }
+
+    /// Like [`WeightedTrie::try_insert`], returning the spelling the word is
+    /// stored under so side maps can be keyed by it.
+    ///
+    /// That is the normalized word, or the existing spelling it was deduped
+    /// into.
+    fn insert_stored(&mut self, word: String, weight: i32) -> Result<String, TrieError> {
+        self.check_word(&word)?;
+        let word = self.stored_spelling(word);
+        if let Some(policy) = self.dedup {
+            if self.find_weight(&word).is_some() {
+                self.update_weights(&[(&word, weight)]);
+                return Ok(word);
+            }
+            self.dedup_keys.insert(policy.key(&word), word.clone());
+        }
+        if let Some(merge) = self.merge_fn.clone() {
+            if self.find_weight(&word).is_some() {
+                self.combine_weights(&[(&word, weight)], &*merge);
+                return Ok(word);
+            }
+        }
+        let key = fold_key(&word, self.fold_case);
+        self.check_children(&key)?;
+        self.insert_along(key.chars(), &word, weight);
+        Ok(word)
+    }
+
+    /// The spelling `word` is stored under: normalized, composed under NFC
+    /// and, with a dedup policy, the variant already stored for its dedup key.
+    fn stored_spelling(&self, mut word: String) -> String {
+        let normalized = self.normalize(&word);
+        if normalized != word.as_str() {
+            word = normalized.into_owned();
+        }
+        #[cfg(feature = "unicode-normalization")]
+        if self.nfc && !unicode_normalization::is_nfc(&word) {
+            word = word.nfc().collect();
+        }
+        if let Some(policy) = self.dedup {
+            if let Some(existing) = self.dedup_keys.get(&policy.key(&word)) {
+                // the stored spelling may have been removed since
+                if self.find_weight(existing).is_some() {
+                    return existing.clone();
+                }
+            }
+        }
+        word
+    }
+
+    /// Inserts `word_display` under the path spelled by `chars`.
+    ///
+    /// The chars are used as given, without trimming or case folding, so
+    /// callers that already hold a normalized key can skip re-parsing it.
+    /// Keys that [`WeightedTrie::insert`] would drop are skipped: empty keys,
+    /// keys longer than [`WeightedTrie::with_max_word_len`] and keys exceeding
+    /// [`WeightedTrie::with_max_children_per_node`].
+    pub fn insert_chars(&mut self, chars: &[char], word_display: &str, weight: i32) {
+        if chars.is_empty() || self.max_word_len.is_some_and(|max| chars.len() > max) {
+            return;
+        }
+        let key: String = chars.iter().collect();
+        if self.check_children(&key).is_err() {
+            return;
+        }
+        if fold_key(word_display, self.fold_case) != key {
+            self.display_keys.insert(word_display.to_owned(), key);
+        }
+        self.insert_along(chars.iter().copied(), word_display, weight);
+    }
+
+    /// Inserts `word` and associates it with an external `id`, such as its
+    /// index in the caller's list, for [`WeightedTrie::search_ids`].
+    ///
+    /// Inserting the same word again replaces its id.
+    pub fn insert_with_id(&mut self, word: String, weight: i32, id: u32) -> Result<(), TrieError> {
+        let key = self.insert_stored(word, weight)?;
+        self.ids.insert(key, id);
+        Ok(())
+    }
+
+    /// Inserts `word` tagged with `category` for
+    /// [`WeightedTrie::search_in_category`].
+    ///
+    /// Inserting the same word again replaces its category.
+    pub fn insert_categorized(
+        &mut self,
+        word: String,
+        weight: i32,
+        category: u16,
+    ) -> Result<(), TrieError> {
+        let key = self.insert_stored(word, weight)?;
+        self.categories.insert(key, category);
+        Ok(())
+    }
+
+    /// Inserts `word` into namespace `ns`, for [`WeightedTrie::search_ns`].
+    ///
+    /// The word is stored behind the namespace and a `'\u{1f}'` separator, so
+    /// `ns` must not contain that char. Plain searches still see namespaced
+    /// words, with the namespace in front.
+    pub fn insert_ns(&mut self, ns: &str, word: String, weight: i32) {
+        self.insert(namespaced(ns, &word), weight);
+    }
+
+    /// Inserts `display` under `key`, so searches match on the key and return
+    /// the display string.
+    ///
+    /// The key is trimmed and case folded like a search prefix, unlike
+    /// [`WeightedTrie::insert_chars`], and checked like the word of
+    /// [`WeightedTrie::try_insert`], so empty or too long keys and keys
+    /// exceeding [`WeightedTrie::with_max_children_per_node`] are rejected.
+    pub fn insert_keyed(
+        &mut self,
+        key: &str,
+        display: String,
+        weight: i32,
+    ) -> Result<(), TrieError> {
+        self.check_word(key)?;
+        let key = self.key(key).into_owned();
+        self.check_children(&key)?;
+        if fold_key(&display, self.fold_case) != key {
+            self.display_keys.insert(display.clone(), key.clone());
+        }
+        self.insert_along(key.chars(), &display, weight);
+        Ok(())
+    }
+
+    /// Inserts `word` with several ranking signals for
+    /// [`WeightedTrie::search_composite`].
+    ///
+    /// The first signal is the weight used everywhere else, like `search`.
+    pub fn insert_composite(&mut self, word: String, weights: &[i32]) -> Result<(), TrieError> {
+        let key = self.insert_stored(word, weights.first().copied().unwrap_or(0))?;
+        self.sub_weights.insert(key, weights.to_vec());
+        Ok(())
+    }
+
+    fn insert_along(&mut self, key: impl Iterator<Item = char>, word: &str, weight: i32) {
+        self.invalidate();
+        if self.recency_first {
+            self.touch(word);
+        }
+        let order = &self.order;
+        let recency_first = self.recency_first;
+        let (max_suggestions, scaled_cap) = (self.max_suggestions, self.depth_scaled_cap);
+        let (mut added, mut freed) = (0, 0);
+        let nodes = &mut self.nodes;
+        let mut id = ROOT;
+        for (depth, c) in (1..).zip(key) {
+            let len = nodes.len();
+            id = child_or_insert(nodes, id, c);
+            if nodes.len() > len {
+                added += node_bytes::<S>();
+            }
+            let node = &mut nodes[id as usize];
+            let cap = suggestion_cap(max_suggestions, scaled_cap, depth);
+            let entry = (weight, word.to_owned());
+            // insert after any equal entries so a run of equal weights is an append;
+            // ranking last or strictly first are common enough to skip the search
+            let suggestions = &node.suggestions;
+            let pos = match (suggestions.first(), suggestions.last()) {
+                _ if recency_first => {
+                    suggestions.partition_point(|probe| order(probe, &entry) == Ordering::Less)
+                }
+                (Some(_), Some(last)) if order(last, &entry) != Ordering::Greater => {
+                    suggestions.len()
+                }
+                (Some(first), _) if order(&entry, first) == Ordering::Less => 0,
+                _ => suggestions.partition_point(|probe| order(probe, &entry) != Ordering::Greater),
+            };
+            if pos >= cap {
+                continue;
+            }
+            added += entry_bytes(word);
+            node.suggestions.insert(pos, entry);
+            if node.suggestions.len() > cap {
+                if let Some((_, popped)) = node.suggestions.pop() {
+                    freed += entry_bytes(&popped);
+                }
+            }
+            debug_assert_sorted(order, &node.suggestions);
+        }
+        nodes[id as usize].terminal = true;
+        if let Some(budget) = self.memory_budget {
+            self.estimated_bytes = (self.estimated_bytes + added).saturating_sub(freed);
+            if self.estimated_bytes > budget {
+                self.enforce_memory_budget(budget);
+            }
+        }
+    }
+
+    fn enforce_memory_budget(&mut self, budget: usize) {
+        // other mutations don't maintain the running estimate, so start exact
+        self.estimated_bytes = self.estimated_memory_bytes();
+        while self.estimated_bytes > budget {
+            let Some(lowest) = self
+                .words()
+                .min_by_key(|&&(weight, _)| weight)
+                .map(|(_, word)| word.clone())
+            else {
+                break;
+            };
+            self.retain_words(|(_, word)| *word != lowest);
+            self.estimated_bytes = self.estimated_memory_bytes();
+        }
+    }
+
+    /// Inserts the word and weight `parse` returns for each line of `r`,
+    /// skipping lines it returns `None` for.
+    ///
+    /// Stops at the first read error, keeping the words inserted before it.
+    #[cfg(feature = "std")]
+    pub fn insert_from_lines(
+        &mut self,
+        r: impl std::io::BufRead,
+        parse: impl Fn(&str) -> Option<(String, i32)>,
+    ) -> std::io::Result<()> {
+        for line in r.lines() {
+            if let Some((word, weight)) = parse(&line?) {
+                self.insert(word, weight);
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts every item with [`WeightedTrie::try_insert`], returning whether
+    /// each one was accepted, in input order.
+    pub fn insert_all(&mut self, items: Vec<WeightedString>) -> Vec<bool> {
+        items
+            .into_iter()
+            .map(|ws| self.try_insert(ws.word, ws.weight).is_ok())
+            .collect()
+    }
+
+    /// Inserts the valid inputs like [`WeightedTrie::build_checked`] and returns
+    /// the rejected ones.
+    pub fn extend_checked(
+        &mut self,
+        weighted_strings: Vec<WeightedString>,
+    ) -> Vec<(WeightedString, TrieError)> {
+        let mut rejected = Vec::new();
+        for ws in weighted_strings {
+            let checked =
+                self.check_word(&ws.word)
+                    .and_then(|_| match self.find_weight(&ws.word) {
+                        Some(_) => Err(TrieError::DuplicateWord),
+                        None => Ok(()),
+                    });
+            match checked {
+                Ok(()) => self.insert(ws.word, ws.weight),
+                Err(err) => rejected.push((ws, err)),
+            }
+        }
+        rejected
+    }
+
+    /// Inserts `word` and records `inserted_at` as the time it was last seen.
+    ///
+    /// Only words inserted this way expire, words added with
+    /// [`WeightedTrie::insert`] are kept by [`WeightedTrie::search_since`] and
+    /// [`WeightedTrie::expire`].
+    #[cfg(feature = "std")]
+    pub fn insert_at(
+        &mut self,
+        word: String,
+        weight: i32,
+        inserted_at: Instant,
+    ) -> Result<(), TrieError> {
+        let key = self.insert_stored(word, weight)?;
+        self.inserted_at.insert(key, inserted_at);
+        Ok(())
+    }
+
+    /// Like [`WeightedTrie::search`] but skips words last inserted before `min_time`.
+    #[cfg(feature = "std")]
+    pub fn search_since(&self, prefix: &str, min_time: Instant) -> Vec<String> {
+        self.search_filter(prefix, usize::MAX, |word| {
+            self.inserted_at.get(word).is_none_or(|&at| at >= min_time)
+        })
+    }
+
+    /// Removes every entry of `word`, returning whether it was stored.
+    ///
+    /// This walks the whole trie, so removing many words at once is cheaper
+    /// through [`WeightedTrie::prune_to_top`] and the like.
+    pub fn remove(&mut self, word: &str) -> bool {
+        let word = &*self.normalize(word);
+        if self.find_weight(word).is_none() {
+            return false;
+        }
+        self.retain_words(|(_, stored)| stored != word);
+        true
+    }
+
+    /// Removes every word last inserted before `before`.
+    #[cfg(feature = "std")]
+    pub fn expire(&mut self, before: Instant) {
+        let mut inserted_at = core::mem::take(&mut self.inserted_at);
+        self.retain_words(|(_, word)| inserted_at.get(word).is_none_or(|&at| at >= before));
+        inserted_at.retain(|_, at| *at >= before);
+        self.inserted_at = inserted_at;
+    }
+
+    /// Inserts `word`, or replaces its weight if it is already stored, and
+    /// returns the previous weight like `HashMap::insert`.
+    ///
+    /// A dedup variant or NFC spelling of an already stored word counts as
+    /// that word.
+    pub fn insert_replace(&mut self, word: String, weight: i32) -> Option<i32> {
+        let word = self.stored_spelling(word);
+        match self.find_weight(&word) {
+            Some(previous) => {
+                self.combine_weights(&[(&word, weight)], &|_, weight| weight);
+                Some(previous)
+            }
+            None => {
+                self.insert(word, weight);
+                None
+            }
+        }
+    }
+
+    /// The weight of `word`, inserting it with `default` first if it isn't
+    /// stored, resolving dedup variants like [`WeightedTrie::insert_replace`].
+    pub fn get_or_insert(&mut self, word: String, default: i32) -> i32 {
+        let word = self.stored_spelling(word);
+        if let Some(weight) = self.find_weight(&word) {
+            return weight;
+        }
+        self.insert(word, default);
+        default
+    }
+
+    /// Adds each delta to the weight of its word, ignoring unknown words.
+    ///
+    /// Every node shared by several updated words is re-sorted only once.
+    pub fn update_weights(&mut self, updates: &[(&str, i32)]) {
+        self.combine_weights(updates, &i32::saturating_add);
+    }
+
+    /// The weight of every word, for syncing replicas with
+    /// [`WeightedTrie::apply_weights`] instead of a full serialized trie.
+    #[cfg(feature = "std")]
+    pub fn export_weights(&self) -> HashMap<String, i32> {
+        let mut weights = HashMap::new();
+        for (weight, word) in self.words() {
+            weights.entry(word.clone()).or_insert(*weight);
+        }
+        weights
+    }
+
+    /// Sets the weight of each word of `weights` that is already in the trie,
+    /// re-sorting every affected node once. Unknown words are inserted when
+    /// `insert_unknown` is set and ignored otherwise.
+    #[cfg(feature = "std")]
+    pub fn apply_weights(&mut self, weights: &HashMap<String, i32>, insert_unknown: bool) {
+        let mut known = Vec::new();
+        let mut unknown = Vec::new();
+        for (word, &weight) in weights {
+            match self.find_weight(word) {
+                Some(_) => known.push((word.as_str(), weight)),
+                None => unknown.push((word, weight)),
+            }
+        }
+        self.combine_weights(&known, &|_, weight| weight);
+        if insert_unknown {
+            for (word, weight) in unknown {
+                self.insert(word.clone(), weight);
+            }
+        }
+    }
+
+    /// Merges `other` into this trie, resolving words present in both with
+    /// `policy`.
+    pub fn merge_with<T: ChildHasher>(&mut self, other: WeightedTrie<T>, policy: MergePolicy) {
+        let mut seen = BTreeSet::new();
+        let mut conflicts = Vec::new();
+        for (weight, word) in other.words() {
+            // keep the best ranked entry of a word repeated in `other`
+            if !seen.insert(word.as_str()) {
+                continue;
+            }
+            match self.find_weight(word) {
+                Some(existing) => {
+                    let merged = match policy {
+                        MergePolicy::Sum => existing.saturating_add(*weight),
+                        MergePolicy::Max => existing.max(*weight),
+                        MergePolicy::KeepLeft => existing,
+                        MergePolicy::KeepRight => *weight,
+                    };
+                    conflicts.push((word.as_str(), merged));
+                }
+                None => match other.display_keys.get(word) {
+                    Some(key) => {
+                        let chars: Vec<char> = key.chars().collect();
+                        self.insert_chars(&chars, word, *weight);
+                    }
+                    None => self.insert(word.clone(), *weight),
+                },
+            }
+        }
+        self.combine_weights(&conflicts, &|_, merged| merged);
+    }
+
+    /// Replaces the weight of each word with `combine(weight, value)`, re-sorting
+    /// every node shared by several updated words only once.
+    fn combine_weights(&mut self, updates: &[(&str, i32)], combine: &dyn Fn(i32, i32) -> i32) {
+        self.invalidate();
+        let (display_keys, fold_case) = (&self.display_keys, self.fold_case);
+        let normalized: Vec<(Cow<str>, i32)> = updates
+            .iter()
+            .map(|&(word, delta)| (self.normalize(word), delta))
+            .collect();
+        // (key, word, delta), sorted by key so words sharing a path are contiguous
+        let mut merged: Vec<(Cow<str>, &str, i32)> = normalized
+            .iter()
+            .map(|(word, delta)| (stored_key(display_keys, word, fold_case), &**word, *delta))
+            .collect();
+        merged.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        merged.dedup_by(|next, prev| {
+            let same = next.1 == prev.1;
+            if same {
+                prev.2 = combine(prev.2, next.2);
+            }
+            same
+        });
+
+        let (order, recency_first) = (&self.order, self.recency_first);
+        let resort = |suggestions: &mut [(i32, String)], changed: &[usize]| {
+            if recency_first {
+                move_to_front_of_equals(suggestions, changed, order);
+            } else {
+                suggestions.sort_by(|a, b| order(a, b));
+            }
+            debug_assert_sorted(order, suggestions);
+        };
+
+        // re-sorts a node's suggestions given the indices that were reweighted
+        type Resort<'a> = dyn Fn(&mut [(i32, String)], &[usize]) + 'a;
+        fn update<S: ChildHasher>(
+            node: TrieNodeMut<'_, S>,
+            updates: &[(Cow<str>, &str, i32)],
+            pos: usize,
+            resort: &Resort,
+            display_keys: &BTreeMap<String, String>,
+            fold_case: bool,
+            combine: &dyn Fn(i32, i32) -> i32,
+        ) {
+            let TrieNodeMut { nodes, id } = node;
+            let suggestions = &mut nodes[id as usize].suggestions;
+            let mut changed = Vec::new();
+            for (i, (weight, word)) in suggestions.iter_mut().enumerate() {
+                let key = stored_key(display_keys, word, fold_case);
+                let probe = (key.as_ref(), word.as_str());
+                if let Ok(j) = updates.binary_search_by(|(k, w, _)| (k.as_ref(), *w).cmp(&probe)) {
+                    *weight = combine(*weight, updates[j].2);
+                    changed.push(i);
+                }
+            }
+            if !changed.is_empty() {
+                resort(suggestions, &changed);
+            }
+            // updates are sorted, so words sharing the next char are contiguous
+            let mut rest = updates;
+            while !rest.is_empty() {
+                let c = match rest[0].0[pos..].chars().next() {
+                    Some(c) => c,
+                    None => {
+                        rest = &rest[1..];
+                        continue;
+                    }
+                };
+                let group = rest
+                    .iter()
+                    .take_while(|(k, _, _)| k[pos..].starts_with(c))
+                    .count();
+                if let Some(&child) = nodes[id as usize].children.get(&c) {
+                    update(
+                        TrieNodeMut { nodes, id: child },
+                        &rest[..group],
+                        pos + c.len_utf8(),
+                        resort,
+                        display_keys,
+                        fold_case,
+                        combine,
+                    );
+                }
+                rest = &rest[group..];
+            }
+        }
+        update(
+            TrieNodeMut {
+                nodes: &mut self.nodes,
+                id: ROOT,
+            },
+            &merged,
+            0,
+            &resort,
+            display_keys,
+            fold_case,
+            combine,
+        );
+        if self.recency_first {
+            // renumber in the old order so words moved together keep theirs
+            let mut touched: Vec<(u64, String)> = merged
+                .iter()
+                .filter_map(|(_, word, _)| {
+                    Some((*self.last_touched.get(*word)?, (*word).to_owned()))
+                })
+                .collect();
+            touched.sort_unstable();
+            for (_, word) in touched {
+                self.touch(&word);
+            }
+        }
+    }
+
+    /// Gives `word` the next sequence number, ranking it first among its
+    /// equals under [`TieBreak::RecencyFirst`].
+    fn touch(&mut self, word: &str) {
+        self.next_seq += 1;
+        self.last_touched.insert(word.to_owned(), self.next_seq);
+    }
+
+    pub fn map_weights(&mut self, f: impl Fn(i32) -> i32) {
+        self.invalidate();
+        let order = &self.order;
+        for node in &mut self.nodes {
+            node.suggestions.iter_mut().for_each(|(w, _)| *w = f(*w));
+            // `f` is not required to be monotonic, so the order has to be restored
+            node.suggestions.sort_by(|a, b| order(a, b));
+            debug_assert_sorted(order, &node.suggestions);
+        }
+    }
+
+    /// Learns from a user picking `selected_word` after typing `prefix`:
+    /// the word's weight grows by the selection increment, 1 unless set with
+    /// [`WeightedTrie::with_selection_increment`], and a word not stored yet
+    /// is inserted with the increment as its weight.
+    ///
+    /// Returns the word's new rank among the completions of `prefix`.
+    pub fn record_selection(&mut self, prefix: &str, selected_word: &str) -> Option<usize> {
+        let increment = self.selection_increment;
+        if self.find_weight(selected_word).is_some() {
+            self.update_weights(&[(selected_word, increment)]);
+        } else {
+            self.insert(selected_word.to_owned(), increment);
+        }
+        self.rank_in_prefix(prefix, selected_word)
+    }
+
+    /// Multiplies every weight by `factor`, rounding to the nearest integer, so
+    /// that older weights fade next to freshly added ones.
+    ///
+    /// For `factor >= 0` the scaling is monotonic and cannot reorder words under
+    /// the default order, so nodes are only re-sorted when a custom comparator
+    /// ends up out of order.
+    pub fn decay_weights(&mut self, factor: f64) {
+        self.invalidate();
+        let order = &self.order;
+        for node in &mut self.nodes {
+            for (weight, _) in node.suggestions.iter_mut() {
+                let scaled = *weight as f64 * factor;
+                // `f64::round` needs std; the casts saturate at the i32 bounds
+                *weight = if scaled < 0.0 {
+                    (scaled - 0.5) as i32
+                } else {
+                    (scaled + 0.5) as i32
+                };
+            }
+            if !node
+                .suggestions
+                .is_sorted_by(|a, b| order(a, b) != Ordering::Greater)
+            {
+                node.suggestions.sort_by(|a, b| order(a, b));
+            }
+            debug_assert_sorted(order, &node.suggestions);
+        }
+    }
+
+    /// Keeps the `max_words` highest-weighted distinct words and removes the rest.
+    pub fn prune_to_top(&mut self, max_words: usize) {
+        let mut ranked: Vec<&(i32, String)> = self.words().collect();
+        ranked.sort_by_key(|&&(weight, _)| Reverse(weight));
+        let mut keep = BTreeSet::new();
+        for (_, word) in ranked {
+            if keep.len() == max_words {
+                break;
+            }
+            keep.insert(word.clone());
+        }
+        self.retain_words(|(_, word)| keep.contains(word));
+    }
+
+    /// Keeps the highest-weighted words until their weights add up to
+    /// `fraction` of the total and removes the rest.
+    ///
+    /// Negative weights count as zero towards both sums, which are kept as
+    /// `i64` so large corpora can't overflow them.
+    pub fn prune_to_coverage(&mut self, fraction: f64) {
+        let mut ranked: Vec<&(i32, String)> = self.words().collect();
+        ranked.sort_by_key(|&&(weight, _)| Reverse(weight));
+        let total: i64 = ranked
+            .iter()
+            .map(|&&(weight, _)| weight.max(0) as i64)
+            .sum();
+        let target = total as f64 * fraction;
+        let mut covered = 0;
+        let mut keep = BTreeSet::new();
+        for &(weight, ref word) in ranked {
+            if covered as f64 >= target {
+                break;
+            }
+            covered += weight.max(0) as i64;
+            keep.insert(word.clone());
+        }
+        self.retain_words(|(_, word)| keep.contains(word));
+    }
+
+    /// Keeps only the first `k` suggestions of every node and frees the rest.
+    ///
+    /// Unlike [`WeightedTrie::with_max_suggestions_per_node`] this only affects
+    /// the current contents. Deep prefixes may afterwards return fewer results
+    /// than before, since words that fell out of a node are not recovered.
+    pub fn truncate_suggestions(&mut self, k: usize) {
+        self.invalidate();
+        for node in &mut self.nodes {
+            node.suggestions.truncate(k);
+            node.suggestions.shrink_to_fit();
+        }
+    }
+
+    /// Drops nodes left without suggestions or children and shrinks every
+    /// vector to its length, reclaiming memory after words were removed.
+    pub fn compact(&mut self) {
+        // returns whether the node at `id` is still needed
+        fn compact<S: ChildHasher>(nodes: &mut [Node<S>], id: NodeId) -> bool {
+            let children: Vec<(char, NodeId)> = nodes[id as usize]
+                .children
+                .iter()
+                .map(|(&c, &child)| (c, child))
+                .collect();
+            for (c, child) in children {
+                if !compact(nodes, child) {
+                    nodes[id as usize].children.remove(&c);
+                }
+            }
+            let node = &mut nodes[id as usize];
+            #[cfg(feature = "std")]
+            node.children.shrink_to_fit();
+            node.suggestions.shrink_to_fit();
+            for (_, word) in node.suggestions.iter_mut() {
+                word.shrink_to_fit();
+            }
+            !node.suggestions.is_empty() || !node.children.is_empty() || node.terminal
+        }
+        compact(&mut self.nodes, ROOT);
+        self.reclaim_nodes();
+        self.nodes.shrink_to_fit();
+    }
+
+    /// Repacks the arena with only the nodes still reachable from the root,
+    /// in breadth-first order, freeing the slots of nodes that were cut off.
+    fn reclaim_nodes(&mut self) {
+        let mut old: Vec<Option<Node<S>>> = core::mem::take(&mut self.nodes)
+            .into_iter()
+            .map(Some)
+            .collect();
+        // nodes get their new ids in the order they are queued, which is the
+        // order they are taken out again
+        let mut next = ROOT + 1;
+        let mut queue = VecDeque::from([ROOT]);
+        while let Some(id) = queue.pop_front() {
+            let mut node = old[id as usize].take().expect("every node has one parent");
+            for child in node.children.values_mut() {
+                queue.push_back(*child);
+                *child = next;
+                next += 1;
+            }
+            self.nodes.push(node);
+        }
+    }
+
+    /// Re-derives every node's suggestions from the words that end at each node.
+    ///
+    /// Use it to restore the ordering invariant after manipulating nodes directly.
+    pub fn rebuild_suggestions(&mut self) {
+        // words sharing a list met it first in the same shallowest list, so
+        // the order they are first met in keeps their ties as they were
+        let mut first_seen: BTreeMap<String, usize> = BTreeMap::new();
+        let mut queue = VecDeque::from([self.root()]);
+        while let Some(node) = queue.pop_front() {
+            for (_, word) in node.suggestions() {
+                let next = first_seen.len();
+                first_seen.entry(word.clone()).or_insert(next);
+            }
+            let mut children: Vec<(char, TrieNode<S>)> = node.children().collect();
+            children.sort_by_key(|&(c, _)| c);
+            queue.extend(children.into_iter().map(|(_, child)| child));
+        }
+        let (display_keys, fold_case) = (&self.display_keys, self.fold_case);
+        let mut words = Vec::new();
+        let mut stack = vec![(ROOT, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            let node = &mut self.nodes[id as usize];
+            let suggestions = core::mem::take(&mut node.suggestions);
+            node.terminal = false;
+            for (weight, word) in suggestions {
+                let key = stored_key(display_keys, &word, fold_case);
+                if word_char_len(&key) == depth {
+                    words.push((key.into_owned(), weight, word));
+                }
+            }
+            stack.extend(node.children.values().map(|&child| (child, depth + 1)));
+        }
+        if self.recency_first {
+            // reinserting oldest first puts each word back before the older
+            // words it ties with
+            words.sort_by_key(|(_, _, word)| self.last_touched.get(word).copied());
+        } else {
+            words.sort_by_key(|(_, _, word)| first_seen.get(word).copied());
+        }
+        // put every word back under the key it was stored with, so keyed words
+        // keep their path and no insert-time checks apply again
+        for (key, weight, word) in words {
+            self.insert_along(key.chars(), &word, weight);
+        }
+    }
+
+    /// Serializes the trie for [`crate::SearchableBytes`], see
+    /// [`FrozenTrie::to_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        FrozenTrie::from_trie(self).to_bytes()
+    }
+
+    /// Converts the trie into a compact, read-only [`FrozenTrie`].
+    pub fn freeze(self) -> FrozenTrie {
+        FrozenTrie::from_trie(&self)
+    }
+
+    pub fn root(&self) -> TrieNode<'_, S> {
+        TrieNode::new(&self.nodes, ROOT)
+    }
+
+    pub fn root_mut(&mut self) -> TrieNodeMut<'_, S> {
+        self.invalidate();
+        TrieNodeMut {
+            nodes: &mut self.nodes,
+            id: ROOT,
+        }
+    }
+
+    /// Returns every completion of `prefix`.
+    ///
+    /// The result is guaranteed to be sorted by weight descending, with equal
+    /// weights in insertion order, or by the comparator given to
+    /// [`WeightedTrie::with_order`]. There is no need to sort it again.
+    ///
+    /// Weights are compared as plain `i32`s over the whole range, so negative
+    /// weights rank below zero and `i32::MIN` ranks last.
+    ///
+    /// Short prefixes like `"p"` can have a large share of all words as
+    /// completions, and cloning them dominates the search. When only the
+    /// first few are shown, [`WeightedTrie::search_top_k`] is much faster.
+    pub fn search(&self, prefix: &str) -> Vec<String> {
+        let results: Vec<String> = match self.completion_node(prefix) {
+            Some(node) => node
+                .suggestions()
+                .iter()
+                .map(|(_, word)| word.clone())
+                .collect(),
+            None => vec![],
+        };
+        if let Some(metrics) = &self.metrics {
+            metrics.record(results.len());
+        }
+        results
+    }
+
+    /// Counters of the queries served by [`WeightedTrie::search`] so far, all
+    /// zero unless the trie was created [`WeightedTrie::with_metrics`].
+    pub fn metrics(&self) -> QueryMetrics {
+        self.metrics
+            .as_ref()
+            .map(QueryCounters::snapshot)
+            .unwrap_or_default()
+    }
+
+    /// Top `k` completions of `prefix`, memoized in a small LRU cache so
+    /// repeated queries for popular prefixes skip the walk and the clones.
+    ///
+    /// The cache holds the most recently used prefixes and is invalidated by
+    /// any mutation of the trie.
+    #[cfg(feature = "cache")]
+    pub fn search_cached(&self, prefix: &str, k: usize) -> Vec<String> {
+        let mut guard = self
+            .cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let cache = &mut *guard;
+        if cache.generation != self.generation {
+            cache.entries.clear();
+            cache.generation = self.generation;
+        }
+        cache.tick += 1;
+        if let Some((used, cached_k, results)) = cache.entries.get_mut(prefix) {
+            if *cached_k == k {
+                *used = cache.tick;
+                return results.clone();
+            }
+        }
+        let results: Vec<String> = match self.completion_node(prefix) {
+            Some(node) => node
+                .suggestions()
+                .iter()
+                .take(k)
+                .map(|(_, word)| word.clone())
+                .collect(),
+            None => vec![],
+        };
+        if cache.entries.len() >= SEARCH_CACHE_CAPACITY && !cache.entries.contains_key(prefix) {
+            let lru = cache
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _, _))| *used)
+                .map(|(prefix, _)| prefix.clone());
+            if let Some(lru) = lru {
+                cache.entries.remove(&lru);
+            }
+        }
+        cache
+            .entries
+            .insert(prefix.to_owned(), (cache.tick, k, results.clone()));
+        results
+    }
+
+    /// The ids given to [`WeightedTrie::insert_with_id`] of the completions of
+    /// `prefix`, in the order of [`WeightedTrie::search`]. Completions
+    /// inserted without an id are skipped.
+    pub fn search_ids(&self, prefix: &str) -> Vec<u32> {
+        match self.completion_node(prefix) {
+            Some(node) => node
+                .suggestions()
+                .iter()
+                .filter_map(|(_, word)| self.ids.get(word).copied())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Top `k` completions of `prefix` inserted with
+    /// [`WeightedTrie::insert_categorized`] under `category`.
+    pub fn search_in_category(&self, prefix: &str, category: u16, k: usize) -> Vec<String> {
+        self.search_filter(prefix, k, |word| {
+            self.categories.get(word) == Some(&category)
+        })
+    }
+
+    /// The completions of `prefix` among the words inserted into `ns` with
+    /// [`WeightedTrie::insert_ns`], without the namespace.
+    pub fn search_ns(&self, ns: &str, prefix: &str) -> Vec<String> {
+        if self.shorter_than_min_query(prefix) {
+            return vec![];
+        }
+        match self.find_node(&namespaced(ns, prefix)) {
+            Some(node) => node
+                .suggestions()
+                .iter()
+                .filter_map(|(_, word)| word.split_once(NS_SEPARATOR))
+                .map(|(_, word)| word.to_owned())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// The completions of `prefix` joined with `sep`, ready to print.
+    pub fn search_joined(&self, prefix: &str, sep: &str) -> String {
+        self.search_cow(prefix).join(sep)
+    }
+
+    /// A view rooted at `prefix` for issuing many searches within it.
+    pub fn subtrie(&self, prefix: &str) -> Option<SubTrie<'_, S>> {
+        Some(SubTrie {
+            trie: self,
+            node: self.find_node(prefix)?,
+        })
+    }
+
+    /// Like [`WeightedTrie::search`] but borrows the words from the trie,
+    /// callers needing owned strings can convert them with `into_owned`.
+    pub fn search_cow(&self, prefix: &str) -> Vec<Cow<'_, str>> {
+        match self.completion_node(prefix) {
+            Some(node) => node
+                .suggestions()
+                .iter()
+                .map(|(_, word)| Cow::Borrowed(word.as_str()))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Best effort top `k` completions of `prefix`, returning what was
+    /// gathered once `deadline` passes. The result may hold fewer than `k`
+    /// words, or none if the deadline has already passed.
+    #[cfg(feature = "std")]
+    pub fn search_deadline(&self, prefix: &str, deadline: Instant, k: usize) -> Vec<String> {
+        let Some(node) = self.completion_node(prefix) else {
+            return vec![];
+        };
+        node.suggestions()
+            .iter()
+            .take(k)
+            .take_while(|_| Instant::now() < deadline)
+            .map(|(_, word)| word.clone())
+            .collect()
+    }
+
+    /// Top `k` completions of `prefix` ranked by an external per-word score,
+    /// falling back to the stored weight for words missing from `scores`.
+    ///
+    /// Equal scores keep the order of [`WeightedTrie::search`].
+    #[cfg(feature = "std")]
+    pub fn search_rescored(
+        &self,
+        prefix: &str,
+        scores: &HashMap<&str, f64>,
+        k: usize,
+    ) -> Vec<(String, f64)> {
+        let Some(node) = self.completion_node(prefix) else {
+            return vec![];
+        };
+        let mut scored: Vec<(&str, f64)> = node
+            .suggestions()
+            .iter()
+            .map(|(weight, word)| {
+                let score = scores.get(word.as_str()).copied();
+                (word.as_str(), score.unwrap_or(*weight as f64))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(word, score)| (word.to_owned(), score))
+            .collect()
+    }
+
+    /// Top `k` completions ranked by the dot product of `coeffs` with the
+    /// signals given to [`WeightedTrie::insert_composite`].
+    ///
+    /// Words inserted with a single weight have that weight as their only
+    /// signal; missing signals and coefficients count as zero. Equal scores
+    /// keep the order of [`WeightedTrie::search`].
+    pub fn search_composite(&self, prefix: &str, coeffs: &[f64], k: usize) -> Vec<String> {
+        let Some(node) = self.completion_node(prefix) else {
+            return vec![];
+        };
+        let mut scored: Vec<(f64, &str)> = node
+            .suggestions()
+            .iter()
+            .map(|(weight, word)| {
+                let signals = self
+                    .sub_weights
+                    .get(word)
+                    .map_or(core::slice::from_ref(weight), Vec::as_slice);
+                let score = signals
+                    .iter()
+                    .zip(coeffs)
+                    .map(|(&signal, coeff)| signal as f64 * coeff)
+                    .sum();
+                (score, word.as_str())
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(_, word)| word.to_owned())
+            .collect()
+    }
+
+    /// The first `k` completions of [`WeightedTrie::search`] with their
+    /// weights. Only those `k` are cloned, so the cost doesn't grow with the
+    /// number of completions, which matters most for single-char prefixes.
+    ///
+    /// On a trie with a per-node cap, asking for more than a node filled up
+    /// to its cap lists falls back to [`WeightedTrie::search_exhaustive`] for
+    /// the rest. A node below its cap never dropped anything, so its list is
+    /// returned as is.
+    pub fn search_top_k(&self, prefix: &str, k: usize) -> Vec<(String, i32)> {
+        let Some(node) = self.completion_node(prefix) else {
+            return vec![];
+        };
+        let mut top: Vec<(String, i32)> = node
+            .suggestions()
+            .iter()
+            .take(k)
+            .map(|(weight, word)| (word.clone(), *weight))
+            .collect();
+        // only a full list can have had entries dropped from it
+        let depth = word_char_len(&self.key(prefix));
+        let cap = suggestion_cap(self.max_suggestions, self.depth_scaled_cap, depth);
+        if node.suggestions().len() >= cap && top.len() < k {
+            let missing = k - top.len();
+            let listed: BTreeSet<(String, i32)> = top.iter().cloned().collect();
+            let rest = self
+                .search_exhaustive(prefix)
+                .into_iter()
+                .filter(|entry| !listed.contains(entry));
+            top.extend(rest.take(missing));
+        }
+        top
+    }
+
+    /// Top `k` completions of `prefix`, each with the byte range of the word
+    /// that matched it, for bolding in a UI. With case folding or NFC the
+    /// range can differ in length from `prefix`.
+    ///
+    /// A word from [`WeightedTrie::insert_keyed`] is highlighted where its
+    /// display spells the prefix, which needn't be at its start, and gets an
+    /// empty range at 0 if it doesn't spell it anywhere.
+    pub fn search_highlighted(&self, prefix: &str, k: usize) -> Vec<(String, Range<usize>)> {
+        if self.shorter_than_min_query(prefix) {
+            return vec![];
+        }
+        let key = self.key(prefix);
+        let Some(node) = self.find_node_by_key(&key) else {
+            return vec![];
+        };
+        node.suggestions()
+            .iter()
+            .take(k)
+            .map(|(_, word)| (word.clone(), self.matched_range(word, &key)))
+            .collect()
+    }
+
+    /// Page `page` of the completions of `prefix`, counting from 0, with
+    /// `page_size` completions per page.
+    ///
+    /// With [`TieBreak::StableId`] the pages are the same on every replica
+    /// holding the same words, whatever order they were inserted in.
+    pub fn search_paginated(&self, prefix: &str, page: usize, page_size: usize) -> Vec<String> {
+        match self.completion_node(prefix) {
+            Some(node) => node
+                .suggestions()
+                .iter()
+                .skip(page.saturating_mul(page_size))
+                .take(page_size)
+                .map(|(_, word)| word.clone())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// The longest prefix of `query` that has completions, and those
+    /// completions, as a fallback when `query` itself has none.
+    ///
+    /// The prefix is in the trie's key form, case folded where that applies.
+    /// Nothing matching the first char gives an empty prefix and no words.
+    pub fn longest_matching_prefix(&self, query: &str) -> (String, Vec<String>) {
+        let key = self.key(query);
+        let mut node = self.root();
+        let mut matched = (0, node);
+        for (i, c) in key.char_indices() {
+            match node.child(c) {
+                Some(child) => node = child,
+                None => break,
+            }
+            if !node.suggestions().is_empty() {
+                matched = (i + c.len_utf8(), node);
+            }
+        }
+        let (len, node) = matched;
+        let words = node
+            .suggestions()
+            .iter()
+            .map(|(_, word)| word.clone())
+            .collect();
+        (key[..len].to_owned(), words)
+    }
+
+    /// Top `k` completions of `prefix` strictly longer than it, leaving out
+    /// the word the user already typed in full.
+    pub fn search_extensions(&self, prefix: &str, k: usize) -> Vec<String> {
+        if self.shorter_than_min_query(prefix) {
+            return vec![];
+        }
+        let key = self.key(prefix);
+        let Some(node) = self.find_node_by_key(&key) else {
+            return vec![];
+        };
+        node.suggestions()
+            .iter()
+            .filter(|(_, word)| !self.ends_at(word, key.len()))
+            .take(k)
+            .map(|(_, word)| word.clone())
+            .collect()
+    }
+
+    /// Top `k` completions of `prefix` with the prefix itself first when it
+    /// is a stored word, whatever its weight. The rest keep the order of
+    /// [`WeightedTrie::search`].
+    pub fn search_pin_exact(&self, prefix: &str, k: usize) -> Vec<String> {
+        if self.shorter_than_min_query(prefix) {
+            return vec![];
+        }
+        let key = self.key(prefix);
+        let Some(node) = self.find_node_by_key(&key) else {
+            return vec![];
+        };
+        let (exact, rest): (Vec<_>, Vec<_>) = node
+            .suggestions()
+            .iter()
+            .partition(|(_, word)| self.ends_at(word, key.len()));
+        exact
+            .into_iter()
+            .chain(rest)
+            .take(k)
+            .map(|(_, word)| word.clone())
+            .collect()
+    }
+
+    /// Every completion of `prefix` found by walking its whole subtree, ranked
+    /// like [`WeightedTrie::search`].
+    ///
+    /// With [`WeightedTrie::with_max_suggestions_per_node`] or a depth scaled
+    /// cap a node only lists its top entries, but a word pushed out of a
+    /// short prefix is still listed deeper along its path, so this recovers
+    /// it. Words that fell out of every node are gone for good.
+    pub fn search_exhaustive(&self, prefix: &str) -> Vec<(String, i32)> {
+        let Some(node) = self.completion_node(prefix) else {
+            return vec![];
+        };
+        let mut seen = BTreeSet::new();
+        let mut found: Vec<&(i32, String)> = Vec::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            for entry in node.suggestions() {
+                if seen.insert((entry.1.as_str(), entry.0)) {
+                    found.push(entry);
+                }
+            }
+            stack.extend(node.children().map(|(_, child)| child));
+        }
+        found.sort_by(|a, b| (self.order)(a, b));
+        found
+            .into_iter()
+            .map(|(weight, word)| (word.clone(), *weight))
+            .collect()
+    }
+
+    /// Top `k` completions across all `prefixes`, each word reported once with
+    /// its best ranked weight.
+    ///
+    /// The already sorted suggestion lists are merged lazily, so only as many
+    /// entries are visited as needed to fill `k`.
+    pub fn search_union_top_k(&self, prefixes: &[&str], k: usize) -> Vec<(String, i32)> {
+        let lists = prefixes
+            .iter()
+            .filter_map(|prefix| self.completion_node(prefix))
+            .map(|node| node.suggestions())
+            .collect();
+        self.merge_top_k(lists, k)
+    }
+
+    /// The `k` heaviest words of the whole trie, such as the popular list
+    /// shown before anything is typed.
+    ///
+    /// The list is memoized until the next modification of the trie, so
+    /// repeated calls only clone `k` entries. Without `std` it is merged from
+    /// the first-char nodes on every call.
+    pub fn popular(&self, k: usize) -> Vec<(String, i32)> {
+        let lists = || {
+            self.root()
+                .children()
+                .map(|(_, child)| child.suggestions())
+                .collect()
+        };
+        #[cfg(feature = "std")]
+        {
+            let mut cached = self
+                .popular
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            if let Some((cached_k, top)) = &*cached {
+                if *cached_k >= k {
+                    return top.iter().take(k).cloned().collect();
+                }
+            }
+            let top = self.merge_top_k(lists(), k);
+            *cached = Some((k, top.clone()));
+            top
+        }
+        #[cfg(not(feature = "std"))]
+        self.merge_top_k(lists(), k)
+    }
+
+    /// Merges the sorted `lists` into their top `k` entries, each word once
+    /// with its best ranked weight. Only as many entries are visited as
+    /// needed to fill `k`.
+    fn merge_top_k(&self, mut lists: Vec<&[(i32, String)]>, k: usize) -> Vec<(String, i32)> {
+        let mut seen = BTreeSet::new();
+        let mut merged = Vec::new();
+        while merged.len() < k {
+            let mut next: Option<usize> = None;
+            for (i, list) in lists.iter().enumerate() {
+                let Some(head) = list.first() else { continue };
+                if next.is_none_or(|j| (self.order)(head, &lists[j][0]) == Ordering::Less) {
+                    next = Some(i);
+                }
+            }
+            let Some(i) = next else { break };
+            let (weight, word) = &lists[i][0];
+            lists[i] = &lists[i][1..];
+            if seen.insert(word.as_str()) {
+                merged.push((word.clone(), *weight));
+            }
+        }
+        merged
+    }
+
+    /// Top `k` completions of `query` or of any variant of it with two adjacent
+    /// chars swapped, so `"ipzza"` also completes to `"pizza"`.
+    pub fn search_transpose_tolerant(&self, query: &str, k: usize) -> Vec<String> {
+        let chars: Vec<char> = self.key(query).chars().collect();
+        let mut variants = vec![chars.iter().collect::<String>()];
+        for i in 1..chars.len() {
+            if chars[i - 1] != chars[i] {
+                let mut swapped = chars.clone();
+                swapped.swap(i - 1, i);
+                variants.push(swapped.into_iter().collect());
+            }
+        }
+        let variants: Vec<&str> = variants.iter().map(String::as_str).collect();
+        self.search_union_top_k(&variants, k)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect()
+    }
+
+    /// Top `k` completions of `query`, topped up with stored words within a
+    /// few edits of the whole query when it has fewer than `k`, so a typo
+    /// past the prefix still finds something.
+    ///
+    /// Completions come first in their usual order, then fuzzy matches by
+    /// edit distance and rank, each word once. Queries of up to 2 chars allow
+    /// no edits, up to 5 chars one and longer ones two. The fuzzy pass walks
+    /// every branch within reach of the query, so it only runs when needed.
+    pub fn smart_search(&self, query: &str, k: usize) -> Vec<(String, i32)> {
+        let mut results = self.search_top_k(query, k);
+        if results.len() >= k || self.shorter_than_min_query(query) {
+            return results;
+        }
+        let key = self.key(query);
+        let max_edits = match word_char_len(&key) {
+            0..=2 => 0,
+            3..=5 => 1,
+            _ => 2,
+        };
+        let mut seen: BTreeSet<String> = results.iter().map(|(word, _)| word.clone()).collect();
+        for (_, (weight, word)) in self.fuzzy_matches(&key, max_edits) {
+            if results.len() >= k {
+                break;
+            }
+            if seen.insert(word.clone()) {
+                results.push((word.clone(), *weight));
+            }
+        }
+        results
+    }
+
+    /// Entries of the stored words whose keys are within `max_edits`
+    /// insertions, deletions or substitutions of `key`, with their distance,
+    /// closest first and then in suggestion order.
+    fn fuzzy_matches(&self, key: &str, max_edits: usize) -> Vec<(usize, &(i32, String))> {
+        let query: Vec<char> = key.chars().collect();
+        let mut found = Vec::new();
+        // each node with its path length in bytes and its row of edit
+        // distances between that path and every prefix of the query
+        let mut stack = vec![(self.root(), 0, (0..=query.len()).collect::<Vec<usize>>())];
+        while let Some((node, depth, row)) = stack.pop() {
+            for (c, child) in node.children() {
+                let mut next = Vec::with_capacity(row.len());
+                next.push(row[0] + 1);
+                for (j, &q) in query.iter().enumerate() {
+                    let substitution = row[j] + usize::from(q != c);
+                    next.push(substitution.min(row[j + 1] + 1).min(next[j] + 1));
+                }
+                let child_depth = depth + c.len_utf8();
+                let distance = next[query.len()];
+                if child.is_terminal() && distance <= max_edits {
+                    found.extend(
+                        child
+                            .suggestions()
+                            .iter()
+                            .filter(|(_, word)| self.ends_at(word, child_depth))
+                            .map(|entry| (distance, entry)),
+                    );
+                }
+                if next.iter().min().is_some_and(|&min| min <= max_edits) {
+                    stack.push((child, child_depth, next));
+                }
+            }
+        }
+        found.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| (self.order)(a.1, b.1)));
+        found
+    }
+
+    /// Top `k` completions of each of `prefixes`, computed in parallel.
+    #[cfg(feature = "rayon")]
+    pub fn par_search(&self, prefixes: &[&str], k: usize) -> Vec<Vec<String>>
+    where
+        S: Sync,
+    {
+        use rayon::prelude::*;
+
+        prefixes
+            .par_iter()
+            .map(|prefix| match self.completion_node(prefix) {
+                Some(node) => node
+                    .suggestions()
+                    .iter()
+                    .take(k)
+                    .map(|(_, word)| word.clone())
+                    .collect(),
+                None => vec![],
+            })
+            .collect()
+    }
+
+    /// Every prefix with at least `min` completions, in lexicographic order,
+    /// such as the branchy prefixes worth caching.
+    pub fn prefixes_with_min_completions(&self, min: usize) -> Vec<String> {
+        let mut prefixes = Vec::new();
+        walk_paths(self.root(), &mut String::new(), &mut |prefix, node| {
+            if node.suggestions().len() >= min {
+                prefixes.push(prefix.to_owned());
+            }
+            true
+        });
+        prefixes.sort_unstable();
+        prefixes
+    }
+
+    /// Every prefix of at most `prefix_len` chars that has completions, for a
+    /// front end to reject queries that can't match before reaching the trie.
+    /// A query longer than `prefix_len` can be checked by its first chars.
+    #[cfg(feature = "std")]
+    pub fn export_prefix_bloom(&self, prefix_len: usize) -> HashSet<String> {
+        let mut prefixes = HashSet::new();
+        if prefix_len == 0 {
+            return prefixes;
+        }
+        walk_paths(self.root(), &mut String::new(), &mut |prefix, _| {
+            prefixes.insert(prefix.to_owned());
+            word_char_len(prefix) < prefix_len
+        });
+        prefixes
+    }
+
+    /// Top `k` completions of each prefix, keyed by prefix.
+    #[cfg(feature = "std")]
+    pub fn export_prefix_cache(&self, prefixes: &[&str], k: usize) -> HashMap<String, Vec<String>> {
+        prefixes
+            .iter()
+            .map(|&prefix| {
+                let completions = self
+                    .search_top_k(prefix, k)
+                    .into_iter()
+                    .map(|(word, _)| word)
+                    .collect();
+                (prefix.to_owned(), completions)
+            })
+            .collect()
+    }
+
+    /// Like [`WeightedTrie::search`] but writes into `out`, reusing both the
+    /// vector and the strings already in it.
+    pub fn search_into(&self, prefix: &str, out: &mut Vec<String>) {
+        let suggestions = match self.completion_node(prefix) {
+            Some(node) => node.suggestions(),
+            None => &[],
+        };
+        out.truncate(suggestions.len());
+        for (dst, (_, word)) in out.iter_mut().zip(suggestions) {
+            dst.clone_from(word);
+        }
+        let filled = out.len();
+        out.extend(suggestions[filled..].iter().map(|(_, word)| word.clone()));
+    }
+
+    pub fn search_filter(
+        &self,
+        prefix: &str,
+        k: usize,
+        pred: impl Fn(&str) -> bool,
+    ) -> Vec<String> {
+        match self.completion_node(prefix) {
+            Some(node) => node
+                .suggestions()
+                .iter()
+                .filter(|(_, word)| pred(word))
+                .take(k)
+                .map(|(_, word)| word.clone())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Top `k` completions made only of chars in `allowed`.
+    #[cfg(feature = "std")]
+    pub fn search_alphabet_restricted(
+        &self,
+        prefix: &str,
+        allowed: &HashSet<char>,
+        k: usize,
+    ) -> Vec<String> {
+        self.search_filter(prefix, k, |word| word.chars().all(|c| allowed.contains(&c)))
+    }
+
+    /// Top `k` completions whose length in chars is within `min_len..=max_len`.
+    pub fn search_len_range(
+        &self,
+        prefix: &str,
+        min_len: usize,
+        max_len: usize,
+        k: usize,
+    ) -> Vec<String> {
+        self.search_filter(prefix, k, |word| {
+            (min_len..=max_len).contains(&word_char_len(word))
+        })
+    }
+
+    /// Top `k` completions at most `max_extra_chars` chars longer than `prefix`.
+    pub fn search_within_depth(
+        &self,
+        prefix: &str,
+        max_extra_chars: usize,
+        k: usize,
+    ) -> Vec<String> {
+        let max_len = word_char_len(&self.key(prefix)) + max_extra_chars;
+        self.search_filter(prefix, k, |word| {
+            word_char_len(&self.stored_key(word)) <= max_len
+        })
+    }
+
+    /// Top `k` words matching `pattern` in full, where `?` matches any single
+    /// char.
+    ///
+    /// Every node's suggestions are sorted, so once `k` matches are found a
+    /// subtree whose best word ranks no better than the worst match is skipped.
+    pub fn search_wildcard(&self, pattern: &str, k: usize) -> Vec<(String, i32)> {
+        fn walk<'a, S: ChildHasher>(
+            trie: &WeightedTrie<S>,
+            node: TrieNode<'a, S>,
+            pattern: &[char],
+            depth: usize,
+            k: usize,
+            found: &mut Vec<&'a (i32, String)>,
+        ) {
+            if let (true, Some(best), Some(worst)) =
+                (found.len() == k, node.suggestions().first(), found.last())
+            {
+                if (trie.order)(best, worst) != Ordering::Less {
+                    return;
+                }
+            }
+            match pattern.split_first() {
+                None => {
+                    if let Some(entry) = node
+                        .suggestions()
+                        .iter()
+                        .find(|(_, w)| trie.ends_at(w, depth))
+                    {
+                        let pos = found.partition_point(|probe| {
+                            (trie.order)(probe, entry) != Ordering::Greater
+                        });
+                        found.insert(pos, entry);
+                        found.truncate(k);
+                    }
+                }
+                Some(('?', rest)) => {
+                    let mut children: Vec<(char, TrieNode<S>)> = node.children().collect();
+                    children.sort_by_key(|&(c, _)| c);
+                    for (c, child) in children {
+                        walk(trie, child, rest, depth + c.len_utf8(), k, found);
+                    }
+                }
+                Some((c, rest)) => {
+                    if let Some(child) = node.child(*c) {
+                        walk(trie, child, rest, depth + c.len_utf8(), k, found);
+                    }
+                }
+            }
+        }
+
+        let pattern: Vec<char> = self.key(pattern).chars().collect();
+        let mut found = Vec::new();
+        if k > 0 {
+            walk(self, self.root(), &pattern, 0, k, &mut found);
+        }
+        found
+            .into_iter()
+            .map(|(weight, word)| (word.clone(), *weight))
+            .collect()
+    }
+
+    /// For each of the top `k` completions, the part that follows `prefix`,
+    /// which is empty for a completion equal to the prefix.
+    ///
+    /// The prefix is matched in the word like
+    /// [`WeightedTrie::search_highlighted`], so a keyed word whose display
+    /// doesn't spell it is returned whole.
+    pub fn complete_suffix(&self, prefix: &str, k: usize) -> Vec<String> {
+        let key = self.key(prefix);
+        self.search_top_k(prefix, k)
+            .into_iter()
+            .map(|(word, _)| word[self.matched_range(&word, &key).end..].to_owned())
+            .collect()
+    }
+
+    /// Words that complete both `a` and `b`, which is only possible when one
+    /// of them is a prefix of the other.
+    pub fn completions_common(&self, a: &str, b: &str) -> Vec<String> {
+        let (a, b) = (self.key(a), self.key(b));
+        if b.starts_with(a.as_ref()) {
+            self.search(&b)
+        } else if a.starts_with(b.as_ref()) {
+            self.search(&a)
+        } else {
+            vec![]
+        }
+    }
+
+    /// Completions of `prefix` grouped by the char that follows it, groups
+    /// sorted by char and their words by weight.
+    pub fn completions_grouped(&self, prefix: &str) -> Vec<(char, Vec<String>)> {
+        let node = match self.find_node(prefix) {
+            Some(node) => node,
+            None => return vec![],
+        };
+        let mut groups: Vec<(char, Vec<String>)> = node
+            .children()
+            .map(|(c, child)| {
+                let words = child.suggestions().iter().map(|(_, w)| w.clone()).collect();
+                (c, words)
+            })
+            .collect();
+        groups.sort_by_key(|&(c, _)| c);
+        groups
+    }
+
+    /// Sum of the weights of every completion of `prefix`, accumulated in `i64`
+    /// so it can't overflow.
+    pub fn prefix_weight_sum(&self, prefix: &str) -> i64 {
+        self.find_node(prefix).map_or(0, |node| {
+            node.suggestions()
+                .iter()
+                .map(|&(weight, _)| weight as i64)
+                .sum()
+        })
+    }
+
+    /// Number of completions after each char of `prefix`, in one walk,
+    /// stopping at the first char with no completions.
+    pub fn prefix_count_trail(&self, prefix: &str) -> Vec<usize> {
+        let mut trail = Vec::new();
+        let mut node = self.root();
+        for c in self.key(prefix).chars() {
+            match node.child(c) {
+                Some(child) => node = child,
+                None => break,
+            }
+            trail.push(node.suggestions().len());
+        }
+        trail
+    }
+
+    /// The weight of `word` along with its id and category, if it is stored.
+    pub fn get(&self, word: &str) -> Option<WordEntry> {
+        let weight = self.find_weight(word)?;
+        let word = &*self.normalize(word);
+        Some(WordEntry {
+            weight,
+            id: self.ids.get(word).copied(),
+            category: self.categories.get(word).copied(),
+        })
+    }
+
+    /// Sorts externally generated candidates by their stored weights, ranked
+    /// like [`WeightedTrie::search`], using the trie only as a weight oracle.
+    ///
+    /// Unknown words rank with weight 0 when `keep_unknown` is set and are
+    /// dropped otherwise.
+    pub fn rank_candidates(&self, words: &[&str], keep_unknown: bool) -> Vec<(String, i32)> {
+        let mut ranked: Vec<(i32, String)> = words
+            .iter()
+            .filter_map(|&word| match self.find_weight(word) {
+                Some(weight) => Some((weight, word.to_owned())),
+                None if keep_unknown => Some((0, word.to_owned())),
+                None => None,
+            })
+            .collect();
+        ranked.sort_by(|a, b| (self.order)(a, b));
+        ranked
+            .into_iter()
+            .map(|(weight, word)| (word, weight))
+            .collect()
+    }
+
+    /// Whether `word` is stored with a weight of at least `min`.
+    pub fn contains_word_with_min_weight(&self, word: &str, min: i32) -> bool {
+        self.find_weight(word).is_some_and(|weight| weight >= min)
+    }
+
+    /// Whether `word` is stored, as opposed to only being a prefix of stored
+    /// words. With case folding any spelling of a stored word matches.
+    pub fn contains(&self, word: &str) -> bool {
+        self.find_node(word).is_some_and(|node| node.is_terminal())
+    }
+
+    /// Whether some stored word starts with `prefix`.
+    pub fn contains_prefix(&self, prefix: &str) -> bool {
+        self.find_node(prefix)
+            .is_some_and(|node| node.child_count() > 0 || !node.suggestions().is_empty())
+    }
+
+    /// Whether [`WeightedTrie::search`] returns anything for `prefix`.
+    ///
+    /// Unlike [`WeightedTrie::contains_prefix`] this is false for a path that
+    /// exists but keeps no suggestions, as in a membership-only trie created
+    /// with `WeightedTrie::with_max_suggestions_per_node(0)`.
+    pub fn has_completions(&self, prefix: &str) -> bool {
+        self.find_node(prefix)
+            .is_some_and(|node| !node.suggestions().is_empty())
+    }
+
+    /// Whether any of `prefixes` is a prefix of a stored word, stopping at the
+    /// first one that is.
+    pub fn contains_any_prefix(&self, prefixes: &[&str]) -> bool {
+        prefixes.iter().any(|prefix| self.contains_prefix(prefix))
+    }
+
+    /// The only completion of `prefix`, or `None` if it has none or several.
+    ///
+    /// With [`WeightedTrie::with_max_suggestions_per_node`] set to 1 every
+    /// prefix has at most one stored completion, so this can't tell them apart.
+    pub fn unique_completion(&self, prefix: &str) -> Option<String> {
+        match self.find_node(prefix)?.suggestions() {
+            [(_, word)] => Some(word.clone()),
+            _ => None,
+        }
+    }
+
+    /// Weight of the top completion of `prefix`, without cloning its word.
+    pub fn max_weight(&self, prefix: &str) -> Option<i32> {
+        self.find_node(prefix)?
+            .suggestions()
+            .first()
+            .map(|&(weight, _)| weight)
+    }
+
+    pub fn best(&self, prefix: &str) -> Option<(String, i32)> {
+        self.completion_node(prefix)?
+            .suggestions()
+            .first()
+            .map(|(weight, word)| (word.clone(), *weight))
+    }
+
+    /// Picks a completion of `prefix` with probability proportional to its weight.
+    ///
+    /// Negative weights count as zero, so `None` is returned both for a missing
+    /// prefix and for one whose completions all have non-positive weights.
+    #[cfg(feature = "rand")]
+    pub fn sample(&self, prefix: &str, rng: &mut impl rand::Rng) -> Option<String> {
+        let suggestions = self.find_node(prefix)?.suggestions();
+        let total: u64 = suggestions.iter().map(|&(w, _)| w.max(0) as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target = rng.gen_range(0..total);
+        for (weight, word) in suggestions {
+            let weight = (*weight).max(0) as u64;
+            if target < weight {
+                return Some(word.clone());
+            }
+            target -= weight;
+        }
+        None
+    }
+
+    pub fn alphabet(&self) -> BTreeSet<char> {
+        let mut alphabet = BTreeSet::new();
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            for (c, child) in node.children() {
+                alphabet.insert(c);
+                stack.push(child);
+            }
+        }
+        alphabet
+    }
+
+    /// Renders the trie as a Graphviz DOT graph for debugging.
+    ///
+    /// Nodes are labeled with their suggestion count and drawn as double
+    /// circles when a word ends there; edges are labeled with their char.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph trie {\n");
+        let mut queue = VecDeque::from([(self.root(), 0)]);
+        let mut next_id = 1;
+        while let Some((node, id)) = queue.pop_front() {
+            let shape = if node.is_terminal() {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            let _ = writeln!(
+                dot,
+                "    {id} [label=\"{}\", shape={shape}];",
+                node.suggestions().len()
+            );
+            let mut children: Vec<(char, TrieNode<S>)> = node.children().collect();
+            children.sort_by_key(|&(c, _)| c);
+            for (c, child) in children {
+                let _ = writeln!(
+                    dot,
+                    "    {id} -> {next_id} [label=\"{}\"];",
+                    c.escape_debug()
+                );
+                queue.push_back((child, next_id));
+                next_id += 1;
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Lazily walks the nodes below the root breadth-first, yielding each
+    /// one's `(prefix, depth, suggestion_count)`, with depth in chars and
+    /// siblings in char order, to study the trie's shape level by level.
+    ///
+    /// Unlike the depth-first walks a queue can't share one path buffer, so
+    /// every queued node holds its own prefix.
+    pub fn bfs(&self) -> impl Iterator<Item = (String, usize, usize)> + '_ {
+        let mut queue = VecDeque::from([(self.root(), String::new(), 0)]);
+        core::iter::from_fn(move || loop {
+            let (node, prefix, depth) = queue.pop_front()?;
+            let mut children: Vec<(char, TrieNode<S>)> = node.children().collect();
+            children.sort_by_key(|&(c, _)| c);
+            for (c, child) in children {
+                let mut child_prefix = prefix.clone();
+                child_prefix.push(c);
+                queue.push_back((child, child_prefix, depth + 1));
+            }
+            if depth > 0 {
+                return Some((prefix, depth, node.suggestions().len()));
+            }
+        })
+    }
+
+    /// Calls `f` with the prefix and suggestions of every node below the root,
+    /// parents before children and siblings in char order.
+    pub fn for_each_prefix(&self, mut f: impl FnMut(&str, &[(i32, String)])) {
+        fn visit<S: ChildHasher>(
+            node: TrieNode<S>,
+            prefix: &mut String,
+            f: &mut impl FnMut(&str, &[(i32, String)]),
+        ) {
+            let mut children: Vec<(char, TrieNode<S>)> = node.children().collect();
+            children.sort_by_key(|&(c, _)| c);
+            for (c, child) in children {
+                prefix.push(c);
+                f(prefix, child.suggestions());
+                visit(child, prefix, f);
+                prefix.pop();
+            }
+        }
+        visit(self.root(), &mut String::new(), &mut f);
+    }
+
+    /// Splits the range of stored weights into at most `buckets` equally wide,
+    /// inclusive `(lo, hi, count)` buckets. Bounds are computed in `i64`, so
+    /// the full `i32` range is handled.
+    ///
+    /// Each distinct word counts once, by its best ranked weight.
+    pub fn weights_histogram(&self, buckets: usize) -> Vec<(i32, i32, usize)> {
+        let mut distinct = BTreeMap::new();
+        for (weight, word) in self.words() {
+            distinct.entry(word.as_str()).or_insert(*weight as i64);
+        }
+        let weights: Vec<i64> = distinct.into_values().collect();
+        let (Some(&min), Some(&max)) = (weights.iter().min(), weights.iter().max()) else {
+            return vec![];
+        };
+        if buckets == 0 {
+            return vec![];
+        }
+        let span = max - min + 1;
+        let width = (span + buckets as i64 - 1) / buckets as i64;
+        let mut histogram: Vec<(i32, i32, usize)> = (0..(span + width - 1) / width)
+            .map(|i| {
+                let lo = min + i * width;
+                (lo as i32, (lo + width - 1).min(max) as i32, 0)
+            })
+            .collect();
+        for weight in weights {
+            histogram[((weight - min) / width) as usize].2 += 1;
+        }
+        histogram
+    }
+
+    /// Approximate heap bytes held by the nodes and their suggestions, counted
+    /// by length rather than capacity.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        let mut bytes = 0;
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            bytes += node.child_count() * node_bytes::<S>();
+            bytes += node
+                .suggestions()
+                .iter()
+                .map(|(_, word)| entry_bytes(word))
+                .sum::<usize>();
+            stack.extend(node.children().map(|(_, child)| child));
+        }
+        bytes
+    }
+
+    /// Bytes of [`WeightedTrie::estimated_memory_bytes`] that capping every
+    /// node at `cap` suggestions would free, to weigh
+    /// [`WeightedTrie::with_max_suggestions_per_node`] before enabling it.
+    pub fn estimate_cap_savings(&self, cap: usize) -> usize {
+        let mut bytes = 0;
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            bytes += node
+                .suggestions()
+                .iter()
+                .skip(cap)
+                .map(|(_, word)| entry_bytes(word))
+                .sum::<usize>();
+            stack.extend(node.children().map(|(_, child)| child));
+        }
+        bytes
+    }
+
+    /// Counts structurally identical subtrees, which a DAWG would store once.
+    pub fn duplicate_subtree_stats(&self) -> DawgPotential {
+        // a shape is whether a word ends at the node plus its children's shape ids
+        type Shape = (bool, Vec<(char, usize)>);
+
+        fn shape_id<S: ChildHasher>(
+            node: TrieNode<S>,
+            shapes: &mut BTreeMap<Shape, usize>,
+        ) -> usize {
+            let mut edges: Vec<(char, usize)> = node
+                .children()
+                .map(|(c, child)| (c, shape_id(child, shapes)))
+                .collect();
+            edges.sort_unstable();
+            let next = shapes.len();
+            *shapes.entry((node.is_terminal(), edges)).or_insert(next)
+        }
+
+        let mut shapes = BTreeMap::new();
+        for (_, child) in self.root().children() {
+            shape_id(child, &mut shapes);
+        }
+        DawgPotential {
+            subtrees: self.node_count(),
+            distinct_subtrees: shapes.len(),
+        }
+    }
+
+    /// Number of nodes below the root, one per distinct prefix.
+    pub fn node_count(&self) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            count += node.child_count();
+            stack.extend(node.children().map(|(_, child)| child));
+        }
+        count
+    }
+
+    /// The `top_n` prefixes whose suggestions take the most heap bytes,
+    /// heaviest first, to see where [`WeightedTrie::with_max_suggestions_per_node`]
+    /// would save the most.
+    pub fn heaviest_nodes(&self, top_n: usize) -> Vec<(String, usize)> {
+        let mut nodes = vec![(String::new(), suggestion_bytes(self.root()))];
+        walk_paths(self.root(), &mut String::new(), &mut |prefix, node| {
+            nodes.push((prefix.to_owned(), suggestion_bytes(node)));
+            true
+        });
+        nodes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        nodes.truncate(top_n);
+        nodes
+    }
+
+    pub fn memory_stats_by_depth(&self) -> Vec<DepthStat> {
+        let mut stats: Vec<DepthStat> = Vec::new();
+        let mut stack = vec![(self.root(), 0)];
+        while let Some((node, depth)) = stack.pop() {
+            if stats.len() <= depth {
+                stats.resize_with(depth + 1, DepthStat::default);
+            }
+            let stat = &mut stats[depth];
+            stat.depth = depth;
+            stat.nodes += 1;
+            stat.suggestions += node.suggestions().len();
+            stat.suggestion_bytes += suggestion_bytes(node);
+            stack.extend(node.children().map(|(_, child)| (child, depth + 1)));
+        }
+        stats
+    }
+
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let mut check = |prefix: &str, node: TrieNode<S>| {
+            let mut words = BTreeSet::new();
+            for (_, word) in node.suggestions() {
+                if !words.insert(word.as_str()) {
+                    issues.push(ValidationIssue::DuplicateWord {
+                        prefix: prefix.to_owned(),
+                        word: word.clone(),
+                    });
+                }
+            }
+            for (index, pair) in node.suggestions().windows(2).enumerate() {
+                if (self.order)(&pair[0], &pair[1]) == Ordering::Greater {
+                    issues.push(ValidationIssue::OutOfOrder {
+                        prefix: prefix.to_owned(),
+                        index,
+                    });
+                }
+            }
+            // the root keeps no suggestions of its own, and a cap legitimately
+            // drops words from the lists above them
+            let capped = self.max_suggestions.is_some() || self.depth_scaled_cap.is_some();
+            if !capped && !node.is_root() {
+                for child in node.children().map(|(_, child)| child) {
+                    for (_, word) in child.suggestions() {
+                        if !words.contains(word.as_str()) {
+                            issues.push(ValidationIssue::MissingFromAncestor {
+                                prefix: prefix.to_owned(),
+                                word: word.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+            true
+        };
+        check("", self.root());
+        walk_paths(self.root(), &mut String::new(), &mut check);
+        issues
+    }
+
+    /// Writes every distinct word as a `word\tweight` line in `order`.
+    ///
+    /// A word stored several times is written once, with its best ranked
+    /// weight as in [`WeightedTrie::export_weights`]. Words are written as
+    /// stored, so ones containing a tab or newline won't read back as a
+    /// single line.
+    #[cfg(feature = "std")]
+    pub fn write_tsv(&self, mut w: impl std::io::Write, order: TsvOrder) -> std::io::Result<()> {
+        let mut weights = BTreeMap::new();
+        for (weight, word) in self.words() {
+            weights.entry(word.as_str()).or_insert(*weight);
+        }
+        // the map already yields the words in order
+        let mut words: Vec<(&str, i32)> = weights.into_iter().collect();
+        if order == TsvOrder::Weight {
+            words.sort_by_key(|&(_, weight)| Reverse(weight));
+        }
+        for (word, weight) in words {
+            writeln!(w, "{}\t{}", word, weight)?;
+        }
+        Ok(())
+    }
+
+    /// What changed from this trie to `other`, such as the words whose cached
+    /// completions need invalidating after a rebuild.
+    ///
+    /// A word stored several times is compared by its best ranked weight.
+    pub fn diff<'a, T: ChildHasher>(&'a self, other: &'a WeightedTrie<T>) -> TrieDiff<'a> {
+        fn weights<'a>(words: impl Iterator<Item = &'a (i32, String)>) -> BTreeMap<&'a str, i32> {
+            let mut weights = BTreeMap::new();
+            for (weight, word) in words {
+                weights.entry(word.as_str()).or_insert(*weight);
+            }
+            weights
+        }
+        let (old, new) = (weights(self.words()), weights(other.words()));
+        let mut diff = TrieDiff::default();
+        for (&word, &weight) in &old {
+            match new.get(word) {
+                None => diff.removed.push((word, weight)),
+                Some(&new_weight) if new_weight != weight => {
+                    diff.reweighted.push((word, weight, new_weight))
+                }
+                Some(_) => {}
+            }
+        }
+        diff.added = new
+            .into_iter()
+            .filter(|(word, _)| !old.contains_key(word))
+            .collect();
+        diff
+    }
+
+    /// Lazily walks the subtree of `prefix`, yielding the key of every word
+    /// stored under it once, in no particular order.
+    ///
+    /// Words are rebuilt from the path they end at rather than read from the
+    /// suggestion lists, so words dropped from every list by a cap are still
+    /// found. The keys are the case folded or [`WeightedTrie::insert_keyed`]
+    /// keys where those apply, not the display strings.
+    pub fn descendants(&self, prefix: &str) -> impl Iterator<Item = String> + '_ {
+        let key = self.key(prefix);
+        let start = self.find_node_by_key(&key);
+        // `None` marks where the walk leaves a node and pops its char
+        let mut stack: Vec<Option<(char, TrieNode<S>)>> = Vec::new();
+        let mut path = key.into_owned();
+        let mut exact = start
+            .filter(|node| node.is_terminal())
+            .map(|_| path.clone());
+        if let Some(node) = start {
+            stack.extend(node.children().map(Some));
+        }
+        core::iter::from_fn(move || {
+            if let Some(word) = exact.take() {
+                return Some(word);
+            }
+            while let Some(frame) = stack.pop() {
+                let Some((c, node)) = frame else {
+                    path.pop();
+                    continue;
+                };
+                path.push(c);
+                stack.push(None);
+                stack.extend(node.children().map(Some));
+                if node.is_terminal() {
+                    return Some(path.clone());
+                }
+            }
+            None
+        })
+    }
+
+    /// Every distinct word, in lexicographic order of their keys and then of
+    /// the spellings sharing a key.
+    pub fn words_sorted(&self) -> Vec<String> {
+        let mut words: Vec<(String, &String)> = Vec::new();
+        walk_paths(self.root(), &mut String::new(), &mut |path, node| {
+            // the words ending at a node are keyed by exactly its path, and
+            // several spellings can share one when case is folded
+            if node.is_terminal() {
+                let ending = node
+                    .suggestions()
+                    .iter()
+                    .filter(|(_, w)| self.stored_key(w) == path);
+                words.extend(ending.map(|(_, word)| (path.to_owned(), word)));
+            }
+            true
+        });
+        words.sort_unstable();
+        words.dedup();
+        words.into_iter().map(|(_, word)| word.clone()).collect()
+    }
+
+    /// Stored words that are prefixes of `query`, shortest first.
+    pub fn prefixes_of(&self, query: &str) -> Vec<(String, i32)> {
+        let mut found = Vec::new();
+        let mut node = self.root();
+        let mut depth = 0;
+        for c in self.key(query).chars() {
+            node = match node.child(c) {
+                Some(child) => child,
+                None => break,
+            };
+            depth += c.len_utf8();
+            if !node.is_terminal() {
+                continue;
+            }
+            if let Some((weight, word)) = node
+                .suggestions()
+                .iter()
+                .find(|(_, w)| self.ends_at(w, depth))
+            {
+                found.push((word.clone(), *weight));
+            }
+        }
+        found
+    }
+
+    /// Ranks the intended word of each `(prefix, word)` case among the
+    /// completions of its prefix, like [`WeightedTrie::rank_in_prefix`].
+    pub fn evaluate(&self, cases: &[(&str, &str)]) -> EvalReport {
+        EvalReport {
+            ranks: cases
+                .iter()
+                .map(|&(prefix, word)| self.rank_in_prefix(prefix, word))
+                .collect(),
+        }
+    }
+
+    pub fn rank_in_prefix(&self, prefix: &str, word: &str) -> Option<usize> {
+        let word = &*self.normalize(word);
+        self.find_node(prefix)?
+            .suggestions()
+            .iter()
+            .position(|(_, w)| w == word)
+    }
+
+    fn check_word(&self, word: &str) -> Result<(), TrieError> {
+        let normalized = &*self.normalize(word);
+        if normalized.is_empty() {
+            return Err(TrieError::EmptyWord);
+        }
+        if let Some(max) = self.max_word_len {
+            let len = word_char_len(normalized);
+            if len > max {
+                return Err(TrieError::WordTooLong { len, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that inserting `key` adds no child to a node that already has
+    /// the maximum number of children.
+    fn check_children(&self, key: &str) -> Result<(), TrieError> {
+        let Some(max) = self.max_children else {
+            return Ok(());
+        };
+        let mut node = self.root();
+        for c in key.chars() {
+            match node.child(c) {
+                Some(child) => node = child,
+                // the rest of the path is new, so its nodes have one child each
+                None if node.child_count() < max => return Ok(()),
+                None => return Err(TrieError::TooManyChildren { max }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Every stored entry, read from the node it ends at.
+    ///
+    /// Caps can drop a word from the lists of its ancestors but not from its
+    /// own node unless longer words outrank it there, in which case its
+    /// weight is gone and it is skipped.
+    fn words(&self) -> impl Iterator<Item = &(i32, String)> {
+        let mut words = Vec::new();
+        walk_paths(self.root(), &mut String::new(), &mut |path, node| {
+            if node.is_terminal() {
+                words.extend(
+                    node.suggestions()
+                        .iter()
+                        .filter(|(_, w)| self.ends_at(w, path.len())),
+                );
+            }
+            true
+        });
+        words.into_iter()
+    }
+
+    fn retain_words(&mut self, keep: impl Fn(&(i32, String)) -> bool) {
+        self.invalidate();
+        // returns whether the node at `id` is still needed
+        fn retain<S: ChildHasher>(
+            node: TrieNodeMut<'_, S>,
+            depth: usize,
+            keep: &impl Fn(&(i32, String)) -> bool,
+            ends_here: &impl Fn(&str, usize) -> bool,
+            dropped: &mut Vec<String>,
+        ) -> bool {
+            let TrieNodeMut { nodes, id } = node;
+            let children: Vec<(char, NodeId)> = nodes[id as usize]
+                .children
+                .iter()
+                .map(|(&c, &child)| (c, child))
+                .collect();
+            for (c, child) in children {
+                let child = TrieNodeMut {
+                    nodes: &mut *nodes,
+                    id: child,
+                };
+                if !retain(child, depth + c.len_utf8(), keep, ends_here, dropped) {
+                    nodes[id as usize].children.remove(&c);
+                }
+            }
+            let node = &mut nodes[id as usize];
+            let ended = node.terminal && node.suggestions.iter().any(|(_, w)| ends_here(w, depth));
+            node.suggestions.retain(|entry| {
+                let kept = keep(entry);
+                if !kept && ends_here(&entry.1, depth) {
+                    dropped.push(entry.1.clone());
+                }
+                kept
+            });
+            if ended && !node.suggestions.iter().any(|(_, w)| ends_here(w, depth)) {
+                node.terminal = false;
+            }
+            node.terminal || !node.children.is_empty() || !node.suggestions.is_empty()
+        }
+        let (display_keys, fold_case) = (&self.display_keys, self.fold_case);
+        let ends_here =
+            |word: &str, depth: usize| stored_key(display_keys, word, fold_case).len() == depth;
+        let mut dropped = Vec::new();
+        let root = TrieNodeMut {
+            nodes: &mut self.nodes,
+            id: ROOT,
+        };
+        retain(root, 0, &keep, &ends_here, &mut dropped);
+        self.reclaim_nodes();
+        // an entry can be dropped while another of the same word is kept
+        let gone: BTreeSet<String> = dropped
+            .into_iter()
+            .filter(|word| self.find_weight(word).is_none())
+            .collect();
+        self.forget(&gone);
+    }
+
+    /// Drops everything the side maps hold about `words`, which are no longer
+    /// stored, so inserting them again starts afresh.
+    fn forget(&mut self, words: &BTreeSet<String>) {
+        if words.is_empty() {
+            return;
+        }
+        for word in words {
+            self.display_keys.remove(word);
+            self.sub_weights.remove(word);
+            self.ids.remove(word);
+            self.categories.remove(word);
+            self.last_touched.remove(word);
+            #[cfg(feature = "std")]
+            self.inserted_at.remove(word);
+        }
+        self.dedup_keys.retain(|_, stored| !words.contains(stored));
+    }
+
+    /// Marks cached search results as stale; every method that can change
+    /// search results calls it.
+    fn invalidate(&mut self) {
+        #[cfg(feature = "cache")]
+        {
+            self.generation += 1;
+        }
+        #[cfg(feature = "std")]
+        {
+            *self
+                .popular
+                .get_mut()
+                .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+        }
+    }
+
+    fn find_weight(&self, word: &str) -> Option<i32> {
+        let word = &*self.normalize(word);
+        self.find_node_by_key(&self.stored_key(word))?
+            .suggestions()
+            .iter()
+            .find(|(_, w)| w == word)
+            .map(|&(weight, _)| weight)
+    }
+
+    /// The node whose suggestions complete `prefix`, or `None` when `prefix`
+    /// is shorter than [`WeightedTrie::with_min_query_len`].
+    fn completion_node(&self, prefix: &str) -> Option<TrieNode<'_, S>> {
+        if self.shorter_than_min_query(prefix) {
+            return None;
+        }
+        self.find_node(prefix)
+    }
+
+    fn find_node(&self, prefix: &str) -> Option<TrieNode<'_, S>> {
+        self.find_node_by_key(&self.key(prefix))
+    }
+
+    fn find_node_by_key(&self, key: &str) -> Option<TrieNode<'_, S>> {
+        let mut node = self.root();
+        for c in key.chars() {
+            node = node.child(c)?;
+        }
+        Some(node)
+    }
+
+    fn normalize<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.key_options().normalize(s)
+    }
+
+    /// The chars `s` is stored under.
+    fn key<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.key_options().key(s)
+    }
+
+    /// Whether `prefix` is too short for [`WeightedTrie::with_min_query_len`].
+    fn shorter_than_min_query(&self, prefix: &str) -> bool {
+        self.key_options().shorter_than_min_query(prefix)
+    }
+
+    /// Byte range of stored `word` that spells `key`, a prefix of its stored
+    /// key.
+    ///
+    /// A word inserted under a key of its own is searched for a span whose key
+    /// is `key`, and gets the empty range `0..0` when its display has none.
+    fn matched_range(&self, word: &str, key: &str) -> Range<usize> {
+        // the shortest start of `s` whose key is at least as long as `key`
+        let spanning = |s: &str| {
+            s.char_indices()
+                .map(|(i, c)| i + c.len_utf8())
+                .find(|&end| self.key(&s[..end]).len() >= key.len())
+        };
+        if key.is_empty() {
+            return 0..0;
+        }
+        if !self.display_keys.contains_key(word) {
+            if self.stored_key(word) == word {
+                return 0..key.len();
+            }
+            return 0..spanning(word).unwrap_or(word.len());
+        }
+        for (start, _) in word.char_indices().filter(|(_, c)| !c.is_whitespace()) {
+            let rest = &word[start..];
+            if let Some(end) = spanning(rest).filter(|&end| self.key(&rest[..end]) == key) {
+                return start..start + end;
+            }
+        }
+        0..0
+    }
+
+    /// Whether stored `word` ends at a node whose path is `depth` bytes long.
+    fn ends_at(&self, word: &str, depth: usize) -> bool {
+        self.stored_key(word).len() == depth
+    }
+
+    /// The key stored `word` was inserted under.
+    fn stored_key<'a>(&'a self, word: &'a str) -> Cow<'a, str> {
+        stored_key(&self.display_keys, word, self.fold_case)
+    }
+
+    pub(crate) fn key_options(&self) -> KeyOptions {
+        KeyOptions {
+            trim: self.trim,
+            collapse_whitespace: self.collapse_whitespace,
+            fold_case: self.fold_case,
+            #[cfg(feature = "unicode-normalization")]
+            nfc: self.nfc,
+            #[cfg(not(feature = "unicode-normalization"))]
+            nfc: false,
+            min_query_len: self.min_query_len,
+        }
+    }
+}
+
+/// How a trie turns text into the chars it is stored and searched under,
+/// kept apart from the tree so [`FrozenTrie`] searches the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct KeyOptions {
+    pub(crate) trim: bool,
+    pub(crate) collapse_whitespace: bool,
+    pub(crate) fold_case: bool,
+    pub(crate) nfc: bool,
+    pub(crate) min_query_len: usize,
+}
+
+impl KeyOptions {
+    const TRIM: u32 = 1;
+    const COLLAPSE_WHITESPACE: u32 = 1 << 1;
+    const FOLD_CASE: u32 = 1 << 2;
+    const NFC: u32 = 1 << 3;
+
+    fn normalize<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let s = if self.trim { s.trim() } else { s };
+        if self.collapse_whitespace {
+            collapse_whitespace(s)
+        } else {
+            Cow::Borrowed(s)
+        }
+    }
+
+    pub(crate) fn key<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let s = self.normalize(s);
+        #[cfg(feature = "unicode-normalization")]
+        if self.nfc && !unicode_normalization::is_nfc(&s) {
+            let composed: String = s.nfc().collect();
+            return Cow::Owned(fold_key(&composed, self.fold_case).into_owned());
+        }
+        match s {
+            Cow::Borrowed(s) => fold_key(s, self.fold_case),
+            Cow::Owned(s) => Cow::Owned(fold_key(&s, self.fold_case).into_owned()),
+        }
+    }
+
+    pub(crate) fn shorter_than_min_query(&self, prefix: &str) -> bool {
+        self.min_query_len > 0 && word_char_len(&self.normalize(prefix)) < self.min_query_len
+    }
+
+    /// The boolean options as the bits of one header field.
+    pub(crate) fn flags(&self) -> u32 {
+        [
+            (self.trim, Self::TRIM),
+            (self.collapse_whitespace, Self::COLLAPSE_WHITESPACE),
+            (self.fold_case, Self::FOLD_CASE),
+            (self.nfc, Self::NFC),
+        ]
+        .into_iter()
+        .filter(|&(set, _)| set)
+        .fold(0, |flags, (_, bit)| flags | bit)
+    }
+
+    /// Reads back [`KeyOptions::flags`], or `None` for unknown bits and for
+    /// NFC without the `unicode-normalization` feature to apply it.
+    pub(crate) fn from_flags(flags: u32, min_query_len: usize) -> Option<KeyOptions> {
+        let known = Self::TRIM | Self::COLLAPSE_WHITESPACE | Self::FOLD_CASE | Self::NFC;
+        let nfc = flags & Self::NFC != 0;
+        if flags & !known != 0 || nfc && !cfg!(feature = "unicode-normalization") {
+            return None;
+        }
+        Some(KeyOptions {
+            trim: flags & Self::TRIM != 0,
+            collapse_whitespace: flags & Self::COLLAPSE_WHITESPACE != 0,
+            fold_case: flags & Self::FOLD_CASE != 0,
+            nfc,
+            min_query_len,
+        })
+    }
+}
+
+/// Checks in debug builds that `suggestions` is in `order`, the invariant
+/// the insert fast paths and binary search rely on.
+///
+/// Duplicate words aren't checked for: without a dedup or merge policy they
+/// are allowed, and even with one [`WeightedTrie::insert_chars`] and
+/// [`WeightedTrie::insert_keyed`] store a word as given.
+fn debug_assert_sorted(order: &SuggestionOrder, suggestions: &[(i32, String)]) {
+    debug_assert!(
+        suggestions.is_sorted_by(|a, b| order(a, b) != Ordering::Greater),
+        "suggestions out of order: {suggestions:?}"
+    );
+}
+
+/// Re-sorts `suggestions` after the entries at the ascending indices `changed`
+/// were reweighted, placing each of them before the unchanged entries it ties
+/// with.
+fn move_to_front_of_equals(
+    suggestions: &mut [(i32, String)],
+    changed: &[usize],
+    order: &SuggestionOrder,
+) {
+    let mut moved = Vec::with_capacity(changed.len());
+    let mut kept = Vec::with_capacity(suggestions.len() - changed.len());
+    let mut changed = changed.iter().peekable();
+    for (i, entry) in suggestions.iter_mut().enumerate() {
+        let entry = core::mem::take(entry);
+        if changed.next_if(|&&j| j == i).is_some() {
+            moved.push(entry);
+        } else {
+            kept.push(entry);
+        }
+    }
+    moved.sort_by(|a, b| order(a, b));
+    let mut kept = kept.into_iter().peekable();
+    let mut merged = Vec::with_capacity(suggestions.len());
+    for entry in moved {
+        while let Some(next) = kept.next_if(|next| order(next, &entry) == Ordering::Less) {
+            merged.push(next);
+        }
+        merged.push(entry);
+    }
+    merged.extend(kept);
+    for (slot, entry) in suggestions.iter_mut().zip(merged) {
+        *slot = entry;
+    }
+}
+
+/// Walks the subtree below `node` depth-first, calling `visit` with each node
+/// and its path, and descending only where `visit` returns `true`.
+///
+/// The path is kept in the single `path` buffer, pushing a char on the way
+/// down and popping it on the way back up, so the walk allocates nothing per
+/// node. Traversals that need each node's prefix build on this instead of
+/// cloning a `String` for every child.
+fn walk_paths<'a, S: ChildHasher>(
+    node: TrieNode<'a, S>,
+    path: &mut String,
+    visit: &mut impl FnMut(&str, TrieNode<'a, S>) -> bool,
+) {
+    for (c, child) in node.children() {
+        path.push(c);
+        if visit(path, child) {
+            walk_paths(child, path, visit);
+        }
+        path.pop();
+    }
+}
+
+fn namespaced(ns: &str, word: &str) -> String {
+    let mut namespaced = String::with_capacity(ns.len() + NS_SEPARATOR.len_utf8() + word.len());
+    namespaced.push_str(ns);
+    namespaced.push(NS_SEPARATOR);
+    namespaced.push_str(word);
+    namespaced
+}
+
+/// How many suggestions a node `depth` chars deep keeps, `usize::MAX` when
+/// uncapped.
+fn suggestion_cap(
+    max_suggestions: Option<usize>,
+    depth_scaled_cap: Option<usize>,
+    depth: usize,
+) -> usize {
+    let flat_cap = max_suggestions.unwrap_or(usize::MAX);
+    match depth_scaled_cap {
+        Some(base) => flat_cap.min((base / depth.max(1)).max(1)),
+        None => flat_cap,
+    }
+}
+
+fn node_bytes<S>() -> usize {
+    core::mem::size_of::<Node<S>>() + core::mem::size_of::<(char, NodeId)>()
+}
+
+/// Heap bytes of the suggestions of `node` and their strings, by capacity.
+fn suggestion_bytes<S>(node: TrieNode<'_, S>) -> usize {
+    let node = node.node();
+    node.suggestions.capacity() * core::mem::size_of::<(i32, String)>()
+        + node
+            .suggestions
+            .iter()
+            .map(|(_, word)| word.capacity())
+            .sum::<usize>()
+}
+
+fn entry_bytes(word: &str) -> usize {
+    core::mem::size_of::<(i32, String)>() + word.len()
+}
+
+/// A 64-bit FNV-1a hash of `word`, the same on every machine and run, that
+/// [`TieBreak::StableId`] orders equal weights by.
+pub fn stable_id(word: &str) -> u64 {
+    word.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Length of `word` in chars, the unit the trie is keyed on and the one every
+/// length limit and filter uses, so `"café"` is 4 long rather than 5.
+pub fn word_char_len(word: &str) -> usize {
+    word.chars().count()
+}
+
+fn stored_key<'a>(
+    display_keys: &'a BTreeMap<String, String>,
+    word: &'a str,
+    fold_case: bool,
+) -> Cow<'a, str> {
+    match display_keys.get(word) {
+        Some(key) => Cow::Borrowed(key),
+        None => fold_key(word, fold_case),
+    }
+}
+
+/// `s` with every run of whitespace replaced by a single space.
+fn collapse_whitespace(s: &str) -> Cow<'_, str> {
+    let mut prev_space = false;
+    let collapsed = s.chars().all(|c| {
+        let ok = c == ' ' && !prev_space || !c.is_whitespace();
+        prev_space = c.is_whitespace();
+        ok
+    });
+    if collapsed {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if !c.is_whitespace() {
+            out.push(c);
+        } else if !out.ends_with(' ') {
+            out.push(' ');
+        }
+    }
+    Cow::Owned(out)
+}
+
+fn fold_key(word: &str, fold_case: bool) -> Cow<'_, str> {
+    if fold_case {
+        Cow::Owned(word.to_lowercase())
+    } else {
+        Cow::Borrowed(word)
+    }
+}
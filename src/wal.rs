@@ -0,0 +1,121 @@
+use std::io::{self, BufRead, Write};
+
+use crate::trie::WeightedTrie;
+
+/// A [`WeightedTrie`] that appends every mutation to a log before applying
+/// it, so [`WeightedTrie::replay`] can rebuild it after a crash without full
+/// snapshots.
+///
+/// Each mutation is a line of tab separated fields with the word last:
+/// `insert\t<weight>\t<word>`, `remove\t<word>` or `update\t<delta>\t<word>`.
+/// The log is flushed after every record. It only holds the mutations made
+/// through the wrapper, so start from an empty trie or keep a snapshot of the
+/// initial one.
+///
+/// ```
+/// use weighted_trie::{WeightedTrie, WriteAheadLog};
+///
+/// let mut wal = WriteAheadLog::new(WeightedTrie::new(), Vec::new());
+/// wal.insert("pie".to_owned(), 5).unwrap();
+/// wal.insert("pizza".to_owned(), 10).unwrap();
+/// let (trie, log) = wal.into_parts();
+///
+/// let replayed = WeightedTrie::replay(log.as_slice()).unwrap();
+/// assert_eq!(replayed.search("pi"), trie.search("pi"));
+/// ```
+#[derive(Debug)]
+pub struct WriteAheadLog<W: Write> {
+    trie: WeightedTrie,
+    log: W,
+}
+
+impl<W: Write> WriteAheadLog<W> {
+    pub fn new(trie: WeightedTrie, log: W) -> WriteAheadLog<W> {
+        WriteAheadLog { trie, log }
+    }
+
+    /// Logs and applies [`WeightedTrie::insert`].
+    pub fn insert(&mut self, word: String, weight: i32) -> io::Result<()> {
+        self.append("insert", Some(weight), &word)?;
+        self.trie.insert(word, weight);
+        Ok(())
+    }
+
+    /// Logs and applies [`WeightedTrie::remove`].
+    pub fn remove(&mut self, word: &str) -> io::Result<bool> {
+        self.append("remove", None, word)?;
+        Ok(self.trie.remove(word))
+    }
+
+    /// Logs and applies adding `delta` to the weight of `word`, like
+    /// [`WeightedTrie::update_weights`].
+    pub fn update_weight(&mut self, word: &str, delta: i32) -> io::Result<()> {
+        self.append("update", Some(delta), word)?;
+        self.trie.update_weights(&[(word, delta)]);
+        Ok(())
+    }
+
+    pub fn trie(&self) -> &WeightedTrie {
+        &self.trie
+    }
+
+    pub fn into_parts(self) -> (WeightedTrie, W) {
+        (self.trie, self.log)
+    }
+
+    fn append(&mut self, op: &str, value: Option<i32>, word: &str) -> io::Result<()> {
+        if word.contains(['\n', '\r']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "logged words can't contain line breaks",
+            ));
+        }
+        match value {
+            Some(value) => writeln!(self.log, "{op}\t{value}\t{word}")?,
+            None => writeln!(self.log, "{op}\t{word}")?,
+        }
+        self.log.flush()
+    }
+}
+
+impl WeightedTrie {
+    /// Rebuilds a trie from a log written by [`WriteAheadLog`], applying its
+    /// records in order.
+    ///
+    /// A malformed line fails with [`io::ErrorKind::InvalidData`].
+    pub fn replay(r: impl BufRead) -> io::Result<WeightedTrie> {
+        let mut trie = WeightedTrie::new();
+        trie.replay_into(r)?;
+        Ok(trie)
+    }
+
+    /// Applies the records of a log written by [`WriteAheadLog`] to this
+    /// trie, so a log of a configured trie can be replayed onto one built the
+    /// same way.
+    ///
+    /// Records before a malformed line stay applied.
+    pub fn replay_into(&mut self, r: impl BufRead) -> io::Result<()> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid log record {line:?}"),
+            )
+        };
+        for line in r.lines() {
+            let line = line?;
+            let (op, rest) = line.split_once('\t').ok_or_else(|| invalid(&line))?;
+            if op == "remove" {
+                self.remove(rest);
+                continue;
+            }
+            let (value, word) = rest.split_once('\t').ok_or_else(|| invalid(&line))?;
+            let value: i32 = value.parse().map_err(|_| invalid(&line))?;
+            match op {
+                "insert" => self.insert(word.to_owned(), value),
+                "update" => self.update_weights(&[(word, value)]),
+                _ => return Err(invalid(&line)),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use weighted_trie::{LazyTrie, WeightedString, WeightedTrie};
+
+struct TrackingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+// the counters are global, so measurements must not overlap
+static MEASURING: Mutex<()> = Mutex::new(());
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(current, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+fn weighted_strings() -> Vec<WeightedString> {
+    (0..5000)
+        .map(|i| WeightedString {
+            word: format!("w{:06}", (i * 7919) % 5000),
+            weight: i,
+        })
+        .collect()
+}
+
+// Measures the peak heap growth while building, ignoring the input itself.
+fn peak_during(build: impl FnOnce(Vec<WeightedString>) -> WeightedTrie) -> usize {
+    let _guard = MEASURING.lock().unwrap_or_else(|e| e.into_inner());
+    let input = weighted_strings();
+    let baseline = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let trie = build(input);
+    let peak = PEAK.load(Ordering::SeqCst) - baseline;
+    drop(trie);
+    peak
+}
+
+#[test]
+fn test_capped_build_bounds_peak_memory() {
+    let uncapped = peak_during(WeightedTrie::build);
+    let capped = peak_during(|input| {
+        let mut trie = WeightedTrie::with_max_suggestions_per_node(10);
+        trie.extend(input);
+        trie
+    });
+    assert!(
+        capped * 4 < uncapped * 3,
+        "capped build peaked at {capped} bytes, uncapped at {uncapped} bytes"
+    );
+}
+
+// Measures the heap held by what `build` returns, ignoring the input itself.
+fn retained_by<T>(build: impl FnOnce(Vec<WeightedString>) -> T) -> usize {
+    let _guard = MEASURING.lock().unwrap_or_else(|e| e.into_inner());
+    let input = weighted_strings();
+    let baseline = CURRENT.load(Ordering::SeqCst) - input.capacity() * size_of::<WeightedString>();
+    let trie = build(input);
+    let retained = CURRENT.load(Ordering::SeqCst) - baseline;
+    drop(trie);
+    retained
+}
+
+#[test]
+fn test_lazy_trie_retains_less_memory() {
+    let eager = retained_by(WeightedTrie::build);
+    let lazy = retained_by(LazyTrie::build);
+    assert!(
+        lazy * 3 < eager * 2,
+        "lazy trie holds {lazy} bytes, weighted trie {eager} bytes"
+    );
+}
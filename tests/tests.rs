@@ -1,6 +1,13 @@
 #[cfg(test)]
 mod tests {
-    use weighted_trie::{WeightedString, WeightedTrie};
+    use weighted_trie::{
+        stable_id, word_char_len, BuildReport, ByteTrie, ChunkedBuilder, DawgPotential,
+        DedupPolicy, EvalReport, FrozenTrie, LazyTrie, MergePolicy, PrefixSearch, QueryMetrics,
+        SearchableBytes, TieBreak, TrieDiff, TrieError, ValidationIssue, WeightedString,
+        WeightedTrie, WordEntry,
+    };
+    #[cfg(feature = "std")]
+    use weighted_trie::{TsvOrder, WriteAheadLog};
     #[test]
     fn test_weighted_trie_insert() {
         let mut trie = WeightedTrie::new();
@@ -55,4 +62,3102 @@ mod tests {
         let suggestions = trie.search("apple");
         assert_eq!(suggestions.len(), 0);
     }
+
+    #[test]
+    fn test_weighted_trie_eq() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        let built = WeightedTrie::build(vec![
+            WeightedString {
+                word: "pie".to_owned(),
+                weight: 5,
+            },
+            WeightedString {
+                word: "pita".to_owned(),
+                weight: 2,
+            },
+            WeightedString {
+                word: "pi".to_owned(),
+                weight: 1,
+            },
+            WeightedString {
+                word: "pizza".to_owned(),
+                weight: 10,
+            },
+        ]);
+        assert_eq!(trie, built);
+
+        trie.insert("pasta".to_owned(), 3);
+        assert_ne!(trie, built);
+    }
+
+    #[test]
+    fn test_map_weights() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        trie.map_weights(|w| if w > 4 { 0 } else { w * 10 });
+
+        let suggestions = trie.search("pi");
+        assert_eq!(suggestions, vec!["pita", "pi", "pizza", "pie"]);
+
+        let suggestions = trie.search("pit");
+        assert_eq!(suggestions, vec!["pita"]);
+    }
+
+    #[test]
+    fn test_best() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        assert_eq!(trie.best("pi"), Some(("pizza".to_owned(), 10)));
+        assert_eq!(trie.best("pit"), Some(("pita".to_owned(), 2)));
+        assert_eq!(trie.best("apple"), None);
+    }
+
+    #[test]
+    fn test_with_order() {
+        let mut trie = WeightedTrie::with_order(|a, b| a.0.cmp(&b.0));
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        let suggestions = trie.search("pi");
+        assert_eq!(suggestions, vec!["pi", "pita", "pie", "pizza"]);
+
+        trie.map_weights(|w| -w);
+        let suggestions = trie.search("pi");
+        assert_eq!(suggestions, vec!["pizza", "pie", "pita", "pi"]);
+    }
+
+    #[test]
+    fn test_equal_weights_keep_insertion_order() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 1);
+        trie.insert("pita".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+
+        let suggestions = trie.search("pi");
+        assert_eq!(suggestions, vec!["pizza", "pie", "pita", "pi"]);
+    }
+
+    #[test]
+    fn test_get_or_insert() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+
+        assert_eq!(trie.get_or_insert("pizza".to_owned(), 10), 10);
+        assert_eq!(trie.search("piz"), vec!["pizza"]);
+
+        assert_eq!(trie.get_or_insert("pizza".to_owned(), 1), 10);
+        assert_eq!(trie.get_or_insert("pie".to_owned(), 1), 5);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie"]);
+    }
+
+    #[test]
+    fn test_build_presorted() {
+        let weighted_strings = vec![
+            WeightedString {
+                word: "pizza".to_owned(),
+                weight: 10,
+            },
+            WeightedString {
+                word: "pie".to_owned(),
+                weight: 5,
+            },
+            WeightedString {
+                word: "pita".to_owned(),
+                weight: 2,
+            },
+            WeightedString {
+                word: "pi".to_owned(),
+                weight: 1,
+            },
+        ];
+
+        let trie = WeightedTrie::build_presorted(weighted_strings.clone());
+        assert_eq!(trie, WeightedTrie::build(weighted_strings));
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie", "pita", "pi"]);
+    }
+
+    #[test]
+    fn test_search_filter() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        let suggestions = trie.search_filter("pi", 2, |word| !word.contains('z'));
+        assert_eq!(suggestions, vec!["pie", "pita"]);
+
+        let suggestions = trie.search_filter("piz", 2, |word| !word.contains('z'));
+        assert_eq!(suggestions.len(), 0);
+    }
+
+    #[test]
+    fn test_rebuild_suggestions() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        let mut node = trie.root_mut().child_mut('p').unwrap();
+        node.suggestions_raw_mut().reverse();
+        let mut node = node.child_mut('i').unwrap();
+        node.suggestions_raw_mut().swap(0, 3);
+        assert_eq!(trie.search("pi"), vec!["pi", "pie", "pita", "pizza"]);
+
+        trie.rebuild_suggestions();
+        assert_eq!(trie.search("p"), vec!["pizza", "pie", "pita", "pi"]);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie", "pita", "pi"]);
+        assert_eq!(trie.search("pit"), vec!["pita"]);
+
+        // equal weights keep insertion order however the children are walked
+        let ties = ["px", "pc", "pq", "pa", "pm", "pz", "pb", "pk"];
+        let mut trie = WeightedTrie::new();
+        for word in ties {
+            trie.insert(word.to_owned(), 1);
+        }
+        trie.rebuild_suggestions();
+        assert_eq!(trie.search("p"), ties);
+    }
+
+    #[test]
+    fn test_trie_node_accessors() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+
+        let root = trie.root();
+        assert!(root.suggestions().is_empty());
+        assert_eq!(
+            root.children().map(|(c, _)| c).collect::<Vec<_>>(),
+            vec!['p']
+        );
+
+        let node = root.child('p').and_then(|node| node.child('i')).unwrap();
+        assert_eq!(
+            node.suggestions(),
+            &[(10, "pizza".to_owned()), (5, "pie".to_owned())]
+        );
+        let mut chars: Vec<char> = node.children().map(|(c, _)| c).collect();
+        chars.sort();
+        assert_eq!(chars, vec!['e', 'z']);
+        assert!(node.child('t').is_none());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pistachio".to_owned(), -3);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let samples = 10_000;
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..samples {
+            let word = trie.sample("pi", &mut rng).unwrap();
+            *counts.entry(word).or_insert(0) += 1;
+        }
+
+        assert!(!counts.contains_key("pistachio"));
+        for (word, weight) in [("pizza", 10.0), ("pie", 5.0), ("pita", 2.0), ("pi", 1.0)] {
+            let observed = counts[word] as f64 / samples as f64;
+            assert!(
+                (observed - weight / 18.0).abs() < 0.02,
+                "{word}: {observed}"
+            );
+        }
+
+        assert_eq!(trie.sample("pis", &mut rng), None);
+        assert_eq!(trie.sample("apple", &mut rng), None);
+    }
+
+    #[test]
+    fn test_prune_to_top() {
+        let mut trie = WeightedTrie::new();
+        let words = [
+            "pie", "pita", "pi", "pizza", "pasta", "pesto", "apple", "apricot", "banana", "bagel",
+        ];
+        for (weight, word) in words.iter().enumerate() {
+            trie.insert(word.to_string(), weight as i32);
+        }
+
+        trie.prune_to_top(3);
+
+        assert_eq!(trie.search("b"), vec!["bagel", "banana"]);
+        assert_eq!(trie.search("a"), vec!["apricot"]);
+        assert_eq!(trie.search("p").len(), 0);
+        assert!(trie.root().child('p').is_none());
+        assert!(trie
+            .root()
+            .child('a')
+            .unwrap()
+            .child('p')
+            .unwrap()
+            .child('p')
+            .is_none());
+    }
+
+    #[test]
+    fn test_build_with_progress() {
+        let weighted_strings: Vec<WeightedString> = ["pie", "pita", "pi", "pizza", "pasta"]
+            .iter()
+            .enumerate()
+            .map(|(weight, word)| WeightedString {
+                word: word.to_string(),
+                weight: weight as i32,
+            })
+            .collect();
+
+        let mut reported = Vec::new();
+        let trie =
+            WeightedTrie::build_with_progress(weighted_strings.clone(), 2, |n| reported.push(n));
+        assert_eq!(reported, vec![2, 4, 5]);
+        assert_eq!(trie, WeightedTrie::build(weighted_strings.clone()));
+
+        let mut reported = Vec::new();
+        WeightedTrie::build_with_progress(weighted_strings.into_iter().take(4), 2, |n| {
+            reported.push(n)
+        });
+        assert_eq!(reported, vec![2, 4]);
+    }
+
+    #[test]
+    fn test_byte_trie() {
+        let mut trie = ByteTrie::new();
+        trie.insert(&[0xff, 0xfe, 0x01], 5);
+        trie.insert(&[0xff, 0xfe, 0x02, 0x80], 10);
+        trie.insert(&[0xff, 0x00], 1);
+        trie.insert(b"pizza", 3);
+
+        assert_eq!(
+            trie.search(&[0xff]),
+            vec![
+                vec![0xff, 0xfe, 0x02, 0x80],
+                vec![0xff, 0xfe, 0x01],
+                vec![0xff, 0x00]
+            ]
+        );
+        assert_eq!(
+            trie.search(&[0xff, 0xfe, 0x01]),
+            vec![vec![0xff, 0xfe, 0x01]]
+        );
+        assert_eq!(trie.search(b"pi"), vec![b"pizza".to_vec()]);
+        assert_eq!(trie.search(&[0x80]).len(), 0);
+    }
+
+    #[test]
+    fn test_rank_in_prefix() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        assert_eq!(trie.rank_in_prefix("pi", "pizza"), Some(0));
+        assert_eq!(trie.rank_in_prefix("pi", "pie"), Some(1));
+        assert_eq!(trie.rank_in_prefix("pie", "pie"), Some(0));
+        assert_eq!(trie.rank_in_prefix("piz", "pie"), None);
+        assert_eq!(trie.rank_in_prefix("apple", "apple"), None);
+    }
+
+    #[test]
+    fn test_search_into() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        let mut out = Vec::new();
+        trie.search_into("piz", &mut out);
+        assert_eq!(out, vec!["pizza"]);
+
+        trie.search_into("pi", &mut out);
+        assert_eq!(out, vec!["pizza", "pie", "pita", "pi"]);
+
+        trie.search_into("pit", &mut out);
+        assert_eq!(out, vec!["pita"]);
+
+        trie.search_into("apple", &mut out);
+        assert_eq!(out.len(), 0);
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let mut trie = WeightedTrie::new();
+        assert!(trie.alphabet().is_empty());
+
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+
+        let alphabet: Vec<char> = trie.alphabet().into_iter().collect();
+        assert_eq!(alphabet, vec!['a', 'e', 'i', 'p', 'z']);
+    }
+
+    #[test]
+    fn test_with_trim() {
+        let mut trie = WeightedTrie::with_trim();
+        trie.insert(" pizza ".to_owned(), 10);
+        trie.insert("pie\t".to_owned(), 5);
+
+        assert_eq!(trie.search("piz"), vec!["pizza"]);
+        assert_eq!(trie.search(" pi "), vec!["pizza", "pie"]);
+        assert_eq!(trie.search(" ").len(), 0);
+        assert_eq!(trie.get_or_insert("pizza ".to_owned(), 1), 10);
+
+        let mut trie = WeightedTrie::new();
+        trie.insert(" pizza ".to_owned(), 10);
+        assert_eq!(trie.search("piz").len(), 0);
+    }
+
+    #[test]
+    fn test_memory_stats_by_depth() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pa".to_owned(), 1);
+
+        let stats = trie.memory_stats_by_depth();
+        let shape: Vec<(usize, usize, usize)> = stats
+            .iter()
+            .map(|stat| (stat.depth, stat.nodes, stat.suggestions))
+            .collect();
+        assert_eq!(
+            shape,
+            vec![
+                (0, 1, 0),
+                (1, 1, 3),
+                (2, 2, 3),
+                (3, 2, 2),
+                (4, 1, 1),
+                (5, 1, 1)
+            ]
+        );
+        assert_eq!(stats[0].suggestion_bytes, 0);
+        for stat in &stats[1..] {
+            assert!(
+                stat.suggestion_bytes >= stat.suggestions * std::mem::size_of::<(i32, String)>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_len_range() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        assert_eq!(trie.search_len_range("pi", 3, 4, 10), vec!["pie", "pita"]);
+        assert_eq!(trie.search_len_range("pi", 3, 10, 2), vec!["pizza", "pie"]);
+        assert_eq!(trie.search_len_range("pi", 0, 2, 10), vec!["pi"]);
+        assert_eq!(trie.search_len_range("pi", 6, 10, 10).len(), 0);
+    }
+
+    #[test]
+    fn test_update_weights() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        trie.update_weights(&[
+            ("pi", 4),
+            ("pita", 10),
+            ("pizza", -6),
+            ("pi", 2),
+            ("apple", 3),
+        ]);
+
+        assert_eq!(trie.search("pi"), vec!["pita", "pi", "pie", "pizza"]);
+        assert_eq!(trie.best("pit"), Some(("pita".to_owned(), 12)));
+        assert_eq!(trie.best("piz"), Some(("pizza".to_owned(), 4)));
+        assert_eq!(trie.best("pie"), Some(("pie".to_owned(), 5)));
+        assert_eq!(trie.search("apple").len(), 0);
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("apple".to_owned(), 3);
+
+        let prefixes = ["", "p", "pi", "piz", "pita", "a", "apple", "apples", "b"];
+        let expected: Vec<Vec<String>> = prefixes.iter().map(|p| trie.search(p)).collect();
+
+        let frozen: FrozenTrie = trie.freeze();
+        let shared = frozen.clone();
+        for (prefix, expected) in prefixes.iter().zip(expected) {
+            assert_eq!(frozen.search(prefix), expected);
+            assert_eq!(shared.search(prefix), expected);
+        }
+    }
+
+    #[test]
+    fn test_search_is_sorted() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        use std::collections::HashMap;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut trie = WeightedTrie::new();
+        let mut inserted: HashMap<String, (i32, usize)> = HashMap::new();
+        while inserted.len() < 2000 {
+            let len = rng.gen_range(1..8);
+            let word: String = (0..len).map(|_| rng.gen_range('a'..='e')).collect();
+            if inserted.contains_key(&word) {
+                continue;
+            }
+            let weight = rng.gen_range(-20..20);
+            inserted.insert(word.clone(), (weight, inserted.len()));
+            trie.insert(word, weight);
+        }
+
+        for prefix in ["a", "b", "ab", "ca", "dde", "eee", "abcd"] {
+            let results: Vec<(i32, usize)> = trie
+                .search(prefix)
+                .iter()
+                .map(|word| inserted[word])
+                .collect();
+            assert!(!results.is_empty());
+            for pair in results.windows(2) {
+                let ((w1, seq1), (w2, seq2)) = (pair[0], pair[1]);
+                assert!(w1 > w2 || (w1 == w2 && seq1 < seq2), "{prefix}: {pair:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_top_k() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        assert_eq!(
+            trie.search_top_k("pi", 2),
+            vec![("pizza".to_owned(), 10), ("pie".to_owned(), 5)]
+        );
+        assert_eq!(trie.search_top_k("pit", 5), vec![("pita".to_owned(), 2)]);
+        assert_eq!(trie.search_top_k("apple", 5).len(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_export_prefix_cache() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        let cache = trie.export_prefix_cache(&["p", "pit", "apple"], 3);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache["p"], vec!["pizza", "pie", "pita"]);
+        assert_eq!(cache["pit"], vec!["pita"]);
+        assert!(cache["apple"].is_empty());
+    }
+
+    #[test]
+    fn test_completions_common() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        assert_eq!(trie.completions_common("pi", "piz"), vec!["pizza"]);
+        assert_eq!(trie.completions_common("piz", "pi"), vec!["pizza"]);
+        assert_eq!(trie.completions_common("p", "pi"), trie.search("pi"));
+        assert_eq!(trie.completions_common("pie", "piz").len(), 0);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        assert!(trie.validate().is_empty());
+
+        let mut p = trie.root_mut().child_mut('p').unwrap();
+        p.suggestions_raw_mut().swap(0, 1);
+        let mut pi = p.child_mut('i').unwrap();
+        pi.suggestions_raw_mut().push((1, "pi".to_owned()));
+        let mut pit = pi.child_mut('t').unwrap();
+        pit.suggestions_raw_mut().push((0, "pitch".to_owned()));
+
+        let mut issues = trie.validate();
+        issues.sort_by_key(|issue| format!("{issue:?}"));
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::DuplicateWord {
+                    prefix: "pi".to_owned(),
+                    word: "pi".to_owned(),
+                },
+                ValidationIssue::MissingFromAncestor {
+                    prefix: "pi".to_owned(),
+                    word: "pitch".to_owned(),
+                },
+                ValidationIssue::OutOfOrder {
+                    prefix: "p".to_owned(),
+                    index: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_try_insert_errors() {
+        let mut trie = WeightedTrie::with_max_word_len(4);
+        assert_eq!(trie.try_insert("pie".to_owned(), 5), Ok(()));
+        assert_eq!(trie.try_insert("".to_owned(), 1), Err(TrieError::EmptyWord));
+        assert_eq!(
+            trie.try_insert("pizza".to_owned(), 10),
+            Err(TrieError::WordTooLong { len: 5, max: 4 })
+        );
+        trie.insert("pizza".to_owned(), 10);
+        assert_eq!(trie.search("pi"), vec!["pie"]);
+
+        let mut trie = WeightedTrie::with_trim();
+        assert_eq!(
+            trie.try_insert("  ".to_owned(), 1),
+            Err(TrieError::EmptyWord)
+        );
+        assert_eq!(
+            TrieError::WordTooLong { len: 5, max: 4 }.to_string(),
+            "word is 5 chars long, the maximum is 4"
+        );
+    }
+
+    #[test]
+    fn test_max_suggestions_per_node() {
+        fn max_suggestions(trie: &WeightedTrie) -> usize {
+            let mut max = 0;
+            let mut stack = vec![trie.root()];
+            while let Some(node) = stack.pop() {
+                max = max.max(node.suggestions().len());
+                stack.extend(node.children().map(|(_, child)| child));
+            }
+            max
+        }
+
+        let words = ["pie", "pita", "pi", "pizza", "pasta", "pesto", "pear"];
+        let mut trie = WeightedTrie::with_max_suggestions_per_node(2);
+        for (weight, word) in words.iter().enumerate() {
+            trie.insert(word.to_string(), weight as i32);
+            assert!(max_suggestions(&trie) <= 2);
+        }
+        assert_eq!(trie.search("p"), vec!["pear", "pesto"]);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pi"]);
+        assert_eq!(trie.search("piz"), vec!["pizza"]);
+
+        let mut built = WeightedTrie::with_max_suggestions_per_node(2);
+        built.extend(
+            words
+                .iter()
+                .enumerate()
+                .map(|(weight, word)| WeightedString {
+                    word: word.to_string(),
+                    weight: weight as i32,
+                }),
+        );
+        assert_eq!(built, trie);
+    }
+
+    #[test]
+    fn test_completions_grouped() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pitch".to_owned(), 3);
+
+        assert_eq!(
+            trie.completions_grouped("pi"),
+            vec![
+                ('e', vec!["pie".to_owned()]),
+                ('t', vec!["pitch".to_owned(), "pita".to_owned()]),
+                ('z', vec!["pizza".to_owned()]),
+            ]
+        );
+        assert!(trie.completions_grouped("pizza").is_empty());
+        assert!(trie.completions_grouped("apple").is_empty());
+    }
+
+    #[test]
+    fn test_build_checked() {
+        let ws = |word: &str, weight: i32| WeightedString {
+            word: word.to_owned(),
+            weight,
+        };
+        let (trie, rejected) = WeightedTrie::build_checked(vec![
+            ws("pie", 5),
+            ws("", 3),
+            ws("pizza", 10),
+            ws("pie", 7),
+            ws("pi", 1),
+        ]);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie", "pi"]);
+        assert_eq!(
+            rejected,
+            vec![
+                (ws("", 3), TrieError::EmptyWord),
+                (ws("pie", 7), TrieError::DuplicateWord),
+            ]
+        );
+
+        let mut trie = WeightedTrie::with_max_word_len(4);
+        let rejected = trie.extend_checked(vec![ws("pizza", 10), ws("pita", 2)]);
+        assert_eq!(
+            rejected,
+            vec![(ws("pizza", 10), TrieError::WordTooLong { len: 5, max: 4 })]
+        );
+        assert_eq!(trie.search("pi"), vec!["pita"]);
+    }
+
+    #[test]
+    fn test_complete_suffix() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        assert_eq!(trie.complete_suffix("pi", 4), vec!["zza", "e", "ta", ""]);
+        assert_eq!(trie.complete_suffix("p", 2), vec!["izza", "ie"]);
+        assert_eq!(trie.complete_suffix("pizza", 2), vec![""]);
+        assert!(trie.complete_suffix("apple", 2).is_empty());
+    }
+
+    #[test]
+    fn test_words_sorted() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pie".to_owned(), 5);
+        assert_eq!(trie.words_sorted(), vec!["pie", "pita", "pizza"]);
+
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("apple".to_owned(), 3);
+        trie.insert("pie".to_owned(), 4);
+        assert_eq!(
+            trie.words_sorted(),
+            vec!["apple", "pi", "pie", "pita", "pizza"]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_expiring_entries() {
+        use std::time::{Duration, Instant};
+
+        let old = Instant::now();
+        let new = old + Duration::from_secs(60);
+        let cutoff = old + Duration::from_secs(30);
+
+        let mut trie = WeightedTrie::new();
+        trie.insert_at("pizza".to_owned(), 10, old).unwrap();
+        trie.insert_at("pie".to_owned(), 5, new).unwrap();
+        trie.insert("pi".to_owned(), 1);
+
+        assert_eq!(trie.search_since("pi", old), vec!["pizza", "pie", "pi"]);
+        assert_eq!(trie.search_since("pi", cutoff), vec!["pie", "pi"]);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie", "pi"]);
+
+        trie.expire(cutoff);
+        assert_eq!(trie.search("pi"), vec!["pie", "pi"]);
+        assert_eq!(trie.search("piz").len(), 0);
+
+        trie.insert_at("pizza".to_owned(), 10, new).unwrap();
+        assert_eq!(trie.search_since("piz", cutoff), vec!["pizza"]);
+    }
+
+    #[test]
+    fn test_prefixes_of() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pizzeria".to_owned(), 4);
+        trie.insert("pizzerias".to_owned(), 2);
+
+        assert_eq!(
+            trie.prefixes_of("pizzeria"),
+            vec![("pi".to_owned(), 1), ("pizzeria".to_owned(), 4)]
+        );
+        assert_eq!(
+            trie.prefixes_of("pizzas"),
+            vec![("pi".to_owned(), 1), ("pizza".to_owned(), 10)]
+        );
+        assert!(trie.prefixes_of("apple").is_empty());
+        assert!(trie.prefixes_of("p").is_empty());
+    }
+
+    #[test]
+    fn test_with_hasher_matches_default() {
+        use std::hash::{BuildHasherDefault, Hasher};
+
+        #[derive(Default)]
+        struct Fnv(u64);
+
+        impl Hasher for Fnv {
+            fn finish(&self) -> u64 {
+                self.0
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                for &b in bytes {
+                    self.0 = (self.0 ^ b as u64).wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+
+        let words = vec![
+            WeightedString {
+                word: "pie".to_owned(),
+                weight: 5,
+            },
+            WeightedString {
+                word: "pizza".to_owned(),
+                weight: 9,
+            },
+            WeightedString {
+                word: "pickle".to_owned(),
+                weight: 3,
+            },
+            WeightedString {
+                word: "pear".to_owned(),
+                weight: 7,
+            },
+        ];
+        let mut custom = WeightedTrie::with_hasher(BuildHasherDefault::<Fnv>::default());
+        custom.extend(words.clone());
+        let default = WeightedTrie::build(words);
+
+        for prefix in ["p", "pi", "piz", "pe", "x"] {
+            assert_eq!(custom.search(prefix), default.search(prefix));
+        }
+    }
+
+    #[test]
+    fn test_dedup_policy() {
+        let words = ["pizza", "Pizza", "PIZZA", "pizza!", "pizza", "pie"];
+        let distinct = |policy| {
+            let mut trie = WeightedTrie::with_dedup_policy(policy);
+            for word in words {
+                trie.insert(word.to_owned(), 1);
+            }
+            trie.search("p").len() + trie.search("P").len()
+        };
+        let mut plain = WeightedTrie::new();
+        for word in words {
+            plain.insert(word.to_owned(), 1);
+        }
+        assert_eq!(plain.search("p").len() + plain.search("P").len(), 6);
+        assert_eq!(distinct(DedupPolicy::Exact), 5);
+        assert_eq!(distinct(DedupPolicy::CaseInsensitive), 3);
+        assert_eq!(distinct(DedupPolicy::Normalized), 2);
+
+        let mut trie = WeightedTrie::with_dedup_policy(DedupPolicy::CaseInsensitive);
+        trie.insert("pizza".to_owned(), 3);
+        trie.insert("pie".to_owned(), 4);
+        trie.insert("PIZZA".to_owned(), 2);
+        assert_eq!(
+            trie.search_top_k("p", 2),
+            vec![("pizza".to_owned(), 5), ("pie".to_owned(), 4)]
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut trie = WeightedTrie::builder()
+            .order(|a, b| a.0.cmp(&b.0))
+            .trim(true)
+            .max_word_len(6)
+            .max_suggestions_per_node(2)
+            .dedup_policy(DedupPolicy::CaseInsensitive)
+            .build();
+        trie.insert(" pie ".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 9);
+        trie.insert("PIE".to_owned(), 1);
+        trie.insert("pickle".to_owned(), 3);
+        assert_eq!(
+            trie.try_insert("pinwheel".to_owned(), 1),
+            Err(TrieError::WordTooLong { len: 8, max: 6 })
+        );
+        // ascending order, capped at two, "PIE" merged into "pie"
+        assert_eq!(trie.search("pi"), vec!["pickle", "pie"]);
+    }
+
+    #[test]
+    fn test_search_orders_full_weight_range() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [
+            ("pa", 0),
+            ("pb", -5),
+            ("pc", i32::MAX),
+            ("pd", i32::MIN),
+            ("pe", 3),
+            ("pf", -1),
+            ("pg", i32::MIN + 1),
+        ] {
+            trie.insert(word.to_owned(), weight);
+        }
+        assert_eq!(
+            trie.search("p"),
+            vec!["pc", "pe", "pa", "pf", "pb", "pg", "pd"]
+        );
+    }
+
+    #[test]
+    fn test_negative_weights_demote_below_zero() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("spam".to_owned(), -5);
+        trie.insert("spaghetti".to_owned(), 0);
+        trie.insert("spa".to_owned(), 2);
+        assert_eq!(trie.search("spa"), vec!["spa", "spaghetti", "spam"]);
+        assert_eq!(trie.best("sp"), Some(("spa".to_owned(), 2)));
+    }
+
+    #[test]
+    fn test_search_union_top_k() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [
+            ("car", 5),
+            ("cart", 9),
+            ("carrot", 2),
+            ("auto", 7),
+            ("automobile", 3),
+            ("card", 1),
+        ] {
+            trie.insert(word.to_owned(), weight);
+        }
+        // "car" and "ca" overlap, every word under "car" is also under "ca"
+        assert_eq!(
+            trie.search_union_top_k(&["car", "auto", "ca"], 4),
+            vec![
+                ("cart".to_owned(), 9),
+                ("auto".to_owned(), 7),
+                ("car".to_owned(), 5),
+                ("automobile".to_owned(), 3),
+            ]
+        );
+        assert_eq!(trie.search_union_top_k(&["car", "ca"], 10).len(), 4);
+        assert!(trie.search_union_top_k(&["x"], 3).is_empty());
+    }
+
+    #[test]
+    fn test_compact_reclaims_memory() {
+        let mut trie = WeightedTrie::new();
+        for i in 0..2000 {
+            trie.insert(format!("word{i}"), i);
+        }
+        let bytes = |trie: &WeightedTrie| {
+            trie.memory_stats_by_depth()
+                .iter()
+                .map(|stat| stat.suggestion_bytes)
+                .sum::<usize>()
+        };
+        let before = bytes(&trie);
+        trie.prune_to_top(20);
+        trie.compact();
+        assert!(bytes(&trie) * 10 < before);
+        assert_eq!(trie.search("word1").len(), 20);
+        assert_eq!(trie.search("word1999"), vec!["word1999"]);
+        assert!(trie.search("word197").is_empty());
+        assert!(trie.validate().is_empty());
+    }
+
+    #[test]
+    fn test_case_insensitive_folds_whole_word() {
+        let mut trie = WeightedTrie::with_case_insensitive();
+        // 'İ' lowercases to "i\u{307}", two chars
+        trie.insert("İstanbul".to_owned(), 5);
+        trie.insert("Izmir".to_owned(), 3);
+        trie.insert("ice".to_owned(), 4);
+        assert_eq!(trie.search("i"), vec!["İstanbul", "ice", "Izmir"]);
+        assert_eq!(trie.search("i\u{307}st"), vec!["İstanbul"]);
+        assert_eq!(trie.search("İST"), vec!["İstanbul"]);
+        assert!(trie.search("ist").is_empty());
+        assert_eq!(trie.complete_suffix("İs", 1), vec!["tanbul"]);
+        assert_eq!(trie.complete_suffix("IZ", 1), vec!["mir"]);
+        assert_eq!(trie.prefixes_of("ICEBERG"), vec![("ice".to_owned(), 4)]);
+        trie.update_weights(&[("Izmir", 10)]);
+        assert_eq!(trie.best("i"), Some(("Izmir".to_owned(), 13)));
+        assert_eq!(trie.freeze().search("İ"), vec!["İstanbul"]);
+    }
+
+    #[test]
+    fn test_search_within_depth() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [("pizza", 9), ("pizz", 3), ("piz", 2), ("pizzeria", 5)] {
+            trie.insert(word.to_owned(), weight);
+        }
+        assert_eq!(trie.search_within_depth("piz", 1, 10), vec!["pizz", "piz"]);
+        assert_eq!(trie.search_within_depth("piz", 0, 10), vec!["piz"]);
+        assert_eq!(trie.search_within_depth("piz", 1, 1), vec!["pizz"]);
+        assert_eq!(trie.search_within_depth("piz", 5, 10).len(), 4);
+    }
+
+    #[test]
+    fn test_search_cow_borrows_trie_words() {
+        use std::borrow::Cow;
+
+        let trie = WeightedTrie::build(vec![
+            WeightedString {
+                word: "pie".to_owned(),
+                weight: 5,
+            },
+            WeightedString {
+                word: "pizza".to_owned(),
+                weight: 9,
+            },
+        ]);
+        let results = trie.search_cow("pi");
+        let stored = trie
+            .root()
+            .child('p')
+            .unwrap()
+            .child('i')
+            .unwrap()
+            .suggestions();
+        for (result, (_, word)) in results.iter().zip(stored) {
+            assert!(matches!(result, Cow::Borrowed(s) if std::ptr::eq(*s, word.as_str())));
+        }
+        let owned: Vec<String> = results.into_iter().map(Cow::into_owned).collect();
+        assert_eq!(owned, trie.search("pi"));
+    }
+
+    #[test]
+    fn test_node_count() {
+        let mut trie = WeightedTrie::new();
+        assert_eq!(trie.node_count(), 0);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pie".to_owned(), 2);
+        // p, i, e
+        assert_eq!(trie.node_count(), 3);
+        trie.insert("pa".to_owned(), 1);
+        assert_eq!(trie.node_count(), 4);
+    }
+
+    #[test]
+    fn test_search_wildcard() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [
+            ("pizza", 9),
+            ("pozza", 4),
+            ("pizzas", 7),
+            ("pazza", 6),
+            ("plaza", 2),
+        ] {
+            trie.insert(word.to_owned(), weight);
+        }
+        assert_eq!(
+            trie.search_wildcard("p?zza", 10),
+            vec![
+                ("pizza".to_owned(), 9),
+                ("pazza".to_owned(), 6),
+                ("pozza".to_owned(), 4)
+            ]
+        );
+        assert_eq!(
+            trie.search_wildcard("p?zza", 1),
+            vec![("pizza".to_owned(), 9)]
+        );
+        assert_eq!(
+            trie.search_wildcard("?????", 2),
+            vec![("pizza".to_owned(), 9), ("pazza".to_owned(), 6)]
+        );
+        assert_eq!(
+            trie.search_wildcard("?????s", 5),
+            vec![("pizzas".to_owned(), 7)]
+        );
+        assert!(trie.search_wildcard("p?z", 5).is_empty());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pie".to_owned(), 2);
+        assert_eq!(
+            trie.to_dot(),
+            "digraph trie {\n    \
+             0 [label=\"0\", shape=circle];\n    \
+             0 -> 1 [label=\"p\"];\n    \
+             1 [label=\"2\", shape=circle];\n    \
+             1 -> 2 [label=\"i\"];\n    \
+             2 [label=\"2\", shape=doublecircle];\n    \
+             2 -> 3 [label=\"e\"];\n    \
+             3 [label=\"1\", shape=doublecircle];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_chars_routes_by_chars() {
+        let mut trie = WeightedTrie::new();
+        let key: Vec<char> = "creme brulee".chars().collect();
+        trie.insert_chars(&key, "Crème Brûlée", 5);
+        trie.insert("cream".to_owned(), 3);
+        assert_eq!(trie.search("cre"), vec!["Crème Brûlée", "cream"]);
+        assert_eq!(trie.search("creme b"), vec!["Crème Brûlée"]);
+        assert!(trie.search("Crè").is_empty());
+        assert_eq!(
+            trie.prefixes_of("creme brulee!"),
+            vec![("Crème Brûlée".to_owned(), 5)]
+        );
+        trie.update_weights(&[("Crème Brûlée", -4)]);
+        assert_eq!(trie.search("cre"), vec!["cream", "Crème Brûlée"]);
+        assert_eq!(trie.get_or_insert("Crème Brûlée".to_owned(), 100), 1);
+    }
+
+    #[test]
+    fn test_max_weight() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        assert_eq!(trie.max_weight("pi"), Some(10));
+        assert_eq!(trie.max_weight("pit"), Some(2));
+        assert_eq!(trie.max_weight("apple"), None);
+    }
+
+    #[test]
+    fn test_search_composite() {
+        let mut trie = WeightedTrie::new();
+        // (popularity, recency)
+        trie.insert_composite("pizza".to_owned(), &[10, 1]).unwrap();
+        trie.insert_composite("pie".to_owned(), &[4, 8]).unwrap();
+        trie.insert_composite("pita".to_owned(), &[6, 5]).unwrap();
+        trie.insert("pi".to_owned(), 7);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pi", "pita", "pie"]);
+        assert_eq!(
+            trie.search_composite("pi", &[1.0, 0.0], 4),
+            vec!["pizza", "pi", "pita", "pie"]
+        );
+        assert_eq!(
+            trie.search_composite("pi", &[0.0, 1.0], 2),
+            vec!["pie", "pita"]
+        );
+        assert_eq!(
+            trie.search_composite("pi", &[0.5, 1.0], 4),
+            vec!["pie", "pita", "pizza", "pi"]
+        );
+        assert!(trie.search_composite("x", &[1.0], 4).is_empty());
+    }
+
+    #[test]
+    fn test_contains_any_prefix() {
+        let mut trie = WeightedTrie::new();
+        assert!(!trie.contains_prefix(""));
+        trie.insert("pizza".to_owned(), 1);
+        trie.insert("pie".to_owned(), 2);
+        assert!(trie.contains_prefix(""));
+        assert!(trie.contains_prefix("piz"));
+        assert!(!trie.contains_prefix("pix"));
+        assert!(trie.contains_any_prefix(&["apple", "banana", "pi"]));
+        assert!(!trie.contains_any_prefix(&["apple", "pizzas", "q"]));
+        assert!(!trie.contains_any_prefix(&[]));
+    }
+
+    #[test]
+    fn test_truncate_suggestions() {
+        let mut trie = WeightedTrie::new();
+        for i in 0..50 {
+            trie.insert(format!("p{i}"), i);
+        }
+        trie.truncate_suggestions(3);
+        let mut stack = vec![trie.root()];
+        while let Some(node) = stack.pop() {
+            assert!(node.suggestions().len() <= 3);
+            stack.extend(node.children().map(|(_, child)| child));
+        }
+        assert_eq!(trie.search("p"), vec!["p49", "p48", "p47"]);
+        assert_eq!(trie.search("p1"), vec!["p19", "p18", "p17"]);
+    }
+
+    #[test]
+    fn test_for_each_prefix() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pie".to_owned(), 2);
+        trie.insert("ox".to_owned(), 3);
+        let mut table = Vec::new();
+        trie.for_each_prefix(|prefix, suggestions| {
+            let words: Vec<&str> = suggestions.iter().map(|(_, w)| w.as_str()).collect();
+            table.push((prefix.to_owned(), words.join(",")));
+        });
+        assert_eq!(
+            table,
+            vec![
+                ("o".to_owned(), "ox".to_owned()),
+                ("ox".to_owned(), "ox".to_owned()),
+                ("p".to_owned(), "pie,pi".to_owned()),
+                ("pi".to_owned(), "pie,pi".to_owned()),
+                ("pie".to_owned(), "pie".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_aggregated() {
+        let trie = WeightedTrie::build_aggregated(vec![
+            ("pizza".to_owned(), 1),
+            ("pie".to_owned(), 5),
+            ("pizza".to_owned(), 2),
+            ("pizza".to_owned(), 3),
+        ]);
+        assert_eq!(
+            trie.search_top_k("pi", 10),
+            vec![("pizza".to_owned(), 6), ("pie".to_owned(), 5)]
+        );
+    }
+
+    #[test]
+    fn test_search_transpose_tolerant() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pizza".to_owned(), 9);
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("ipad".to_owned(), 1);
+        assert_eq!(trie.search_transpose_tolerant("ipzza", 5), vec!["pizza"]);
+        assert_eq!(
+            trie.search_transpose_tolerant("ip", 5),
+            vec!["pizza", "pie", "ipad"]
+        );
+        assert_eq!(trie.search_transpose_tolerant("pzi", 5), vec!["pizza"]);
+        assert!(trie.search_transpose_tolerant("zzap", 5).is_empty());
+    }
+
+    #[test]
+    fn test_weights_histogram() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [("a", 0), ("b", 1), ("c", 4), ("d", 5), ("e", 9), ("f", 9)] {
+            trie.insert(word.to_owned(), weight);
+        }
+        let histogram = trie.weights_histogram(2);
+        assert_eq!(histogram, vec![(0, 4, 3), (5, 9, 3)]);
+        assert_eq!(
+            trie.weights_histogram(3),
+            vec![(0, 3, 2), (4, 7, 2), (8, 9, 2)]
+        );
+        let total: usize = trie.weights_histogram(4).iter().map(|b| b.2).sum();
+        assert_eq!(total, 6);
+
+        trie.insert("g".to_owned(), i32::MIN);
+        trie.insert("h".to_owned(), i32::MAX);
+        let histogram = trie.weights_histogram(2);
+        assert_eq!(histogram.iter().map(|b| b.2).sum::<usize>(), 8);
+        assert_eq!(histogram[0].0, i32::MIN);
+        assert_eq!(histogram[1].1, i32::MAX);
+        assert!(WeightedTrie::new().weights_histogram(3).is_empty());
+    }
+
+    #[test]
+    fn test_merge_with_policy() {
+        let build = |pairs: &[(&str, i32)]| {
+            let mut trie = WeightedTrie::new();
+            for &(word, weight) in pairs {
+                trie.insert(word.to_owned(), weight);
+            }
+            trie
+        };
+        let left = || build(&[("pizza", 3), ("pie", 5)]);
+        let right = || build(&[("pizza", 4), ("pita", 1)]);
+
+        let mut sum = left();
+        sum.merge_with(right(), MergePolicy::Sum);
+        assert_eq!(
+            sum.search_top_k("pi", 10),
+            vec![
+                ("pizza".to_owned(), 7),
+                ("pie".to_owned(), 5),
+                ("pita".to_owned(), 1)
+            ]
+        );
+
+        let mut max = left();
+        max.merge_with(right(), MergePolicy::Max);
+        assert_eq!(
+            max.search_top_k("pi", 10),
+            vec![
+                ("pie".to_owned(), 5),
+                ("pizza".to_owned(), 4),
+                ("pita".to_owned(), 1)
+            ]
+        );
+
+        let mut keep_left = left();
+        keep_left.merge_with(right(), MergePolicy::KeepLeft);
+        assert_eq!(keep_left.best("piz"), Some(("pizza".to_owned(), 3)));
+        assert!(keep_left.validate().is_empty());
+    }
+
+    #[test]
+    fn test_unique_completion() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pizza".to_owned(), 1);
+        trie.insert("pita".to_owned(), 2);
+        assert_eq!(trie.unique_completion("piz"), Some("pizza".to_owned()));
+        assert_eq!(trie.unique_completion("pizza"), Some("pizza".to_owned()));
+        assert_eq!(trie.unique_completion("pi"), None);
+        assert_eq!(trie.unique_completion("x"), None);
+    }
+
+    #[test]
+    fn test_insert_keeps_order_on_every_branch() {
+        let mut trie = WeightedTrie::new();
+        // last, first, middle and tie-with-first/last inserts at the "p" node
+        for (word, weight) in [
+            ("p1", 5),
+            ("p2", 1),
+            ("p3", 9),
+            ("p4", 4),
+            ("p5", 9),
+            ("p6", 1),
+            ("p7", 3),
+        ] {
+            trie.insert(word.to_owned(), weight);
+        }
+        assert_eq!(
+            trie.search("p"),
+            vec!["p3", "p5", "p1", "p4", "p7", "p2", "p6"]
+        );
+        assert!(trie.validate().is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_search_matches_search() {
+        let mut trie = WeightedTrie::new();
+        for i in 0..500 {
+            trie.insert(format!("w{}", i * 7 % 1000), i);
+        }
+        let prefixes: Vec<String> = (0..100).map(|i| format!("w{i}")).collect();
+        let prefixes: Vec<&str> = prefixes.iter().map(String::as_str).collect();
+        let expected: Vec<Vec<String>> = prefixes
+            .iter()
+            .map(|prefix| trie.search(prefix).into_iter().take(5).collect())
+            .collect();
+        assert_eq!(trie.par_search(&prefixes, 5), expected);
+    }
+
+    #[test]
+    fn test_prune_to_coverage() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [("pizza", 50), ("pie", 30), ("pita", 15), ("pi", 5)] {
+            trie.insert(word.to_owned(), weight);
+        }
+        trie.prune_to_coverage(0.8);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie"]);
+
+        trie.prune_to_coverage(0.0);
+        assert!(trie.search("pi").is_empty());
+    }
+
+    #[test]
+    fn test_length_filters_count_chars() {
+        assert_eq!(word_char_len("café"), 4);
+        assert_eq!("café".len(), 5);
+
+        let mut trie = WeightedTrie::with_max_word_len(4);
+        trie.insert("café".to_owned(), 3);
+        trie.insert("cafe".to_owned(), 2);
+        trie.insert("cafés".to_owned(), 1);
+        assert_eq!(trie.search("caf"), vec!["café", "cafe"]);
+        assert_eq!(trie.search_len_range("caf", 4, 4, 10), vec!["café", "cafe"]);
+        assert_eq!(trie.search_within_depth("caf", 1, 10), vec!["café", "cafe"]);
+    }
+
+    #[test]
+    fn test_decay_weights() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [("pizza", 100), ("pie", 40), ("pita", 9), ("pi", -10)] {
+            trie.insert(word.to_owned(), weight);
+        }
+        trie.decay_weights(0.5);
+        assert_eq!(
+            trie.search_top_k("pi", 10),
+            vec![
+                ("pizza".to_owned(), 50),
+                ("pie".to_owned(), 20),
+                ("pita".to_owned(), 5),
+                ("pi".to_owned(), -5)
+            ]
+        );
+        trie.insert("pickle".to_owned(), 30);
+        assert_eq!(
+            trie.search("pi"),
+            vec!["pizza", "pickle", "pie", "pita", "pi"]
+        );
+
+        trie.decay_weights(-1.0);
+        assert_eq!(
+            trie.search("pi"),
+            vec!["pi", "pita", "pie", "pickle", "pizza"]
+        );
+        assert!(trie.validate().is_empty());
+    }
+
+    #[test]
+    fn test_search_joined() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        assert_eq!(trie.search_joined("pi", ", "), "pizza, pie, pita, pi");
+        assert_eq!(trie.search_joined("x", "\n"), "");
+    }
+
+    #[test]
+    fn test_memory_budget_evicts_lowest_weights() {
+        let budget = 4000;
+        let mut trie = WeightedTrie::with_memory_budget(budget);
+        for i in 0..200 {
+            trie.insert(format!("word{i:03}"), i);
+            assert!(trie.estimated_memory_bytes() <= budget);
+        }
+        // the survivors are exactly the highest weights
+        let kept = trie.search("word");
+        assert!(!kept.is_empty() && kept.len() < 200);
+        assert_eq!(kept[0], "word199");
+        let expected: Vec<String> = (200 - kept.len()..200)
+            .rev()
+            .map(|i| format!("word{i:03}"))
+            .collect();
+        assert_eq!(kept, expected);
+        assert!(trie.validate().is_empty());
+    }
+
+    #[test]
+    fn test_contains_word_with_min_weight() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pie".to_owned(), 2);
+        assert!(trie.contains_word_with_min_weight("pizza", 5));
+        assert!(trie.contains_word_with_min_weight("pizza", 10));
+        assert!(!trie.contains_word_with_min_weight("pie", 5));
+        // a prefix of a stored word is not a word
+        assert!(!trie.contains_word_with_min_weight("piz", 0));
+        assert!(!trie.contains_word_with_min_weight("apple", i32::MIN));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_map() {
+        let map: std::collections::HashMap<String, i32> = [
+            ("pizza", 10),
+            ("pie", 5),
+            ("pita", 2),
+            ("pi", 1),
+            ("pasta", 5),
+        ]
+        .into_iter()
+        .map(|(word, weight)| (word.to_owned(), weight))
+        .collect();
+        let from_map = WeightedTrie::from_map(map);
+        let built = WeightedTrie::build(
+            [
+                ("pasta", 5),
+                ("pi", 1),
+                ("pie", 5),
+                ("pita", 2),
+                ("pizza", 10),
+            ]
+            .into_iter()
+            .map(|(word, weight)| WeightedString {
+                word: word.to_owned(),
+                weight,
+            })
+            .collect(),
+        );
+        for prefix in ["p", "pi", "pa", "piz", "x"] {
+            assert_eq!(from_map.search(prefix), built.search(prefix));
+        }
+    }
+
+    #[test]
+    fn test_subtrie_matches_full_prefix_search() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [
+            ("food/pizza", 9),
+            ("food/pie", 5),
+            ("food/pasta", 7),
+            ("fun/park", 3),
+        ] {
+            trie.insert(word.to_owned(), weight);
+        }
+        let food = trie.subtrie("food/").unwrap();
+        for rel in ["", "p", "pi", "pa", "x"] {
+            assert_eq!(food.search(rel), trie.search(&format!("food/{rel}")));
+        }
+        assert!(trie.subtrie("drinks/").is_none());
+    }
+
+    #[test]
+    fn test_weight_aggregation_does_not_overflow() {
+        let mut trie = WeightedTrie::new();
+        for i in 0..10 {
+            trie.insert(format!("big{i}"), i32::MAX);
+        }
+        trie.insert("small".to_owned(), 1);
+        assert_eq!(trie.prefix_weight_sum("big"), 10 * i32::MAX as i64);
+        assert_eq!(trie.prefix_weight_sum(""), 0);
+        assert_eq!(trie.prefix_weight_sum("s"), 1);
+
+        let histogram = trie.weights_histogram(2);
+        assert_eq!(histogram.iter().map(|b| b.2).sum::<usize>(), 11);
+
+        // 10 * i32::MAX wraps in i32; coverage must still keep the big words
+        trie.prune_to_coverage(0.99);
+        assert_eq!(trie.search("big").len(), 10);
+        assert!(trie.search("s").is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_subtree_stats() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [("walking", 1), ("talking", 2), ("walked", 3), ("talked", 4)] {
+            trie.insert(word.to_owned(), weight);
+        }
+        let stats = trie.duplicate_subtree_stats();
+        assert_eq!(stats.subtrees, trie.node_count());
+        // "alk(ing|ed)" below "w" and "t" is one shape
+        assert_eq!(
+            stats,
+            DawgPotential {
+                subtrees: 18,
+                distinct_subtrees: 8,
+            }
+        );
+        assert_eq!(stats.duplicates(), 10);
+
+        let mut unique = WeightedTrie::new();
+        unique.insert("ab".to_owned(), 1);
+        unique.insert("cd".to_owned(), 1);
+        // the two leaves share a shape, nothing else does
+        assert_eq!(unique.duplicate_subtree_stats().duplicates(), 1);
+    }
+
+    #[test]
+    fn test_insert_keyed() {
+        let mut trie = WeightedTrie::new();
+        trie.insert_keyed("pizza", "🍕 Pizza".to_owned(), 9)
+            .unwrap();
+        trie.insert("pie".to_owned(), 5);
+        assert_eq!(trie.search("pi"), vec!["🍕 Pizza", "pie"]);
+        assert_eq!(trie.search("pizza"), vec!["🍕 Pizza"]);
+        assert!(trie.search("🍕").is_empty());
+        assert_eq!(trie.words_sorted(), vec!["pie", "🍕 Pizza"]);
+
+        let mut folded = WeightedTrie::with_case_insensitive();
+        folded
+            .insert_keyed("Pizza", "🍕 Pizza".to_owned(), 9)
+            .unwrap();
+        assert_eq!(folded.search("PIZ"), vec!["🍕 Pizza"]);
+    }
+
+    #[test]
+    fn test_search_ids() {
+        let records = [
+            WeightedString {
+                word: "pie".to_owned(),
+                weight: 5,
+            },
+            WeightedString {
+                word: "pizza".to_owned(),
+                weight: 10,
+            },
+            WeightedString {
+                word: "pita".to_owned(),
+                weight: 2,
+            },
+        ];
+        let mut trie = WeightedTrie::new();
+        for (id, record) in records.iter().enumerate() {
+            trie.insert_with_id(record.word.clone(), record.weight, id as u32)
+                .unwrap();
+        }
+        trie.insert("pi".to_owned(), 7);
+        let ids = trie.search_ids("pi");
+        assert_eq!(ids, vec![1, 0, 2]);
+        let words: Vec<&str> = ids
+            .iter()
+            .map(|&id| records[id as usize].word.as_str())
+            .collect();
+        assert_eq!(words, vec!["pizza", "pie", "pita"]);
+        assert!(trie.search_ids("x").is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_export_prefix_bloom() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pizza".to_owned(), 1);
+        trie.insert("pie".to_owned(), 2);
+        trie.insert("ox".to_owned(), 3);
+        let prefixes = trie.export_prefix_bloom(3);
+        let mut sorted: Vec<&str> = prefixes.iter().map(String::as_str).collect();
+        sorted.sort();
+        assert_eq!(sorted, vec!["o", "ox", "p", "pi", "pie", "piz"]);
+        assert!(!prefixes.contains("pizz"));
+        assert!(!prefixes.contains("q"));
+        assert!(trie.export_prefix_bloom(0).is_empty());
+    }
+
+    #[test]
+    fn test_search_pin_exact() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        assert_eq!(
+            trie.search_pin_exact("pi", 10),
+            vec!["pi", "pizza", "pie", "pita"]
+        );
+        assert_eq!(trie.search_pin_exact("pi", 2), vec!["pi", "pizza"]);
+        assert_eq!(trie.search_pin_exact("p", 2), vec!["pizza", "pie"]);
+        assert!(trie.search_pin_exact("x", 2).is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_tsv() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+
+        let mut by_weight = Vec::new();
+        trie.write_tsv(&mut by_weight, TsvOrder::Weight).unwrap();
+        assert_eq!(
+            String::from_utf8(by_weight).unwrap(),
+            "pizza\t10\npie\t5\npita\t2\npi\t1\n"
+        );
+
+        let mut by_word = Vec::new();
+        trie.write_tsv(&mut by_word, TsvOrder::Word).unwrap();
+        let tsv = String::from_utf8(by_word).unwrap();
+        assert_eq!(tsv, "pi\t1\npie\t5\npita\t2\npizza\t10\n");
+
+        let parsed = WeightedTrie::build(
+            tsv.lines()
+                .map(|line| {
+                    let (word, weight) = line.split_once('\t').unwrap();
+                    WeightedString {
+                        word: word.to_owned(),
+                        weight: weight.parse().unwrap(),
+                    }
+                })
+                .collect(),
+        );
+        assert_eq!(parsed.search("p"), trie.search("p"));
+        assert_eq!(parsed.search("pi"), trie.search("pi"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_search_deadline() {
+        use std::time::{Duration, Instant};
+
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pita".to_owned(), 2);
+
+        let passed = Instant::now();
+        let partial = trie.search_deadline("pi", passed, 3);
+        assert!(partial.len() < 3);
+
+        let later = Instant::now() + Duration::from_secs(60);
+        assert_eq!(trie.search_deadline("pi", later, 2), vec!["pizza", "pie"]);
+        assert!(trie.search_deadline("x", later, 2).is_empty());
+    }
+
+    #[test]
+    fn test_with_merge_fn() {
+        let mut trie = WeightedTrie::with_merge_fn(|old, new| (old + new) / 2);
+        trie.insert("pie".to_owned(), 4);
+        trie.insert("pizza".to_owned(), 6);
+        trie.insert("pie".to_owned(), 10);
+        assert_eq!(
+            trie.search_top_k("pi", 5),
+            vec![("pie".to_owned(), 7), ("pizza".to_owned(), 6)]
+        );
+        trie.insert("pizza".to_owned(), 20);
+        assert_eq!(
+            trie.search_top_k("pi", 5),
+            vec![("pizza".to_owned(), 13), ("pie".to_owned(), 7)]
+        );
+
+        let mut trie = WeightedTrie::builder().merge_fn(i32::max).build();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pie".to_owned(), 2);
+        assert_eq!(trie.search("pi"), vec!["pie"]);
+    }
+
+    #[test]
+    fn test_heaviest_nodes() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("ox".to_owned(), 1);
+        trie.compact();
+
+        // the root holds no suggestions, every word is listed under its first char
+        let heaviest = trie.heaviest_nodes(2);
+        assert_eq!(heaviest.len(), 2);
+        assert_eq!(heaviest[0].0, "p");
+        assert_eq!(heaviest[1].0, "pi");
+        assert_eq!(heaviest[0].1, heaviest[1].1);
+        assert!(trie.heaviest_nodes(0).is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_insert_from_lines() {
+        use std::io::Cursor;
+
+        let input = Cursor::new("pie:5\npizza:10\n# comment\npita:x\npi:1\n");
+        let mut trie = WeightedTrie::new();
+        trie.insert_from_lines(input, |line| {
+            let (word, weight) = line.split_once(':')?;
+            Some((word.to_owned(), weight.parse().ok()?))
+        })
+        .unwrap();
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie", "pi"]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_search_alphabet_restricted() {
+        use std::collections::HashSet;
+
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        let allowed: HashSet<char> = "abcdefghijklmnopqrstuvwxy".chars().collect();
+        assert_eq!(
+            trie.search_alphabet_restricted("pi", &allowed, 10),
+            vec!["pie", "pita", "pi"]
+        );
+        assert_eq!(
+            trie.search_alphabet_restricted("pi", &allowed, 1),
+            vec!["pie"]
+        );
+    }
+
+    #[test]
+    fn test_searchable_bytes() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("café".to_owned(), 3);
+        trie.insert("cafe".to_owned(), -4);
+
+        let bytes = trie.to_bytes();
+        let view = SearchableBytes::from_bytes(&bytes).unwrap();
+        for prefix in ["", "p", "pi", "piz", "pizza", "caf", "café", "x"] {
+            assert_eq!(view.search(prefix), trie.search(prefix), "{prefix}");
+        }
+
+        assert_eq!(
+            SearchableBytes::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            TrieError::InvalidBytes
+        );
+        assert!(SearchableBytes::from_bytes(b"WTRI").is_err());
+        let mut corrupted = bytes.clone();
+        corrupted[0] = b'X';
+        assert!(SearchableBytes::from_bytes(&corrupted).is_err());
+    }
+
+    #[test]
+    fn test_terminal_nodes() {
+        let mut trie = WeightedTrie::with_max_suggestions_per_node(1);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pi".to_owned(), 1);
+
+        let pi = trie.root().child('p').unwrap().child('i').unwrap();
+        assert!(pi.is_terminal());
+        assert!(!pi.child('z').unwrap().is_terminal());
+        // the cap keeps "pi" out of its own node, the flag still knows it ends there
+        assert_eq!(pi.suggestions(), &[(10, "pizza".to_owned())]);
+        assert!(trie.contains("pi"));
+        assert!(trie.contains("pizza"));
+        assert!(!trie.contains("piz"));
+        assert!(!trie.contains("pizzas"));
+
+        let mut trie = WeightedTrie::new();
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pi".to_owned(), 1);
+        trie.prune_to_top(1);
+        assert!(!trie.contains("pi"));
+        assert!(trie.contains("pizza"));
+    }
+
+    #[test]
+    fn test_chunked_builder() {
+        let weighted_strings: Vec<WeightedString> = ["pie", "pita", "pi", "pizza", "pie", "ox"]
+            .iter()
+            .zip([5, 2, 1, 10, 3, 7])
+            .map(|(word, weight)| WeightedString {
+                word: (*word).to_owned(),
+                weight,
+            })
+            .collect();
+
+        let mut builder = ChunkedBuilder::new();
+        for chunk in weighted_strings.chunks(4) {
+            builder.feed(chunk);
+        }
+        builder.feed(&[]);
+        assert_eq!(builder.finish(), WeightedTrie::build(weighted_strings));
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut old = WeightedTrie::new();
+        old.insert("pie".to_owned(), 5);
+        old.insert("pita".to_owned(), 2);
+        old.insert("ox".to_owned(), 1);
+        let mut new = WeightedTrie::new();
+        new.insert("pie".to_owned(), 8);
+        new.insert("pita".to_owned(), 2);
+        new.insert("ox".to_owned(), 1);
+        new.insert("pizza".to_owned(), 10);
+
+        assert_eq!(
+            old.diff(&new),
+            TrieDiff {
+                added: vec![("pizza", 10)],
+                removed: vec![],
+                reweighted: vec![("pie", 5, 8)],
+            }
+        );
+        let reverse = new.diff(&old);
+        assert_eq!(reverse.removed, vec![("pizza", 10)]);
+        assert_eq!(reverse.reweighted, vec![("pie", 8, 5)]);
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_search_rescored() {
+        use std::collections::HashMap;
+
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pizza".to_owned(), 10);
+
+        let scores = HashMap::new();
+        assert_eq!(
+            trie.search_rescored("pi", &scores, 2),
+            vec![("pizza".to_owned(), 10.0), ("pie".to_owned(), 5.0)]
+        );
+
+        let scores = HashMap::from([("pita", 20.5), ("pizza", 0.5)]);
+        assert_eq!(
+            trie.search_rescored("pi", &scores, 3),
+            vec![
+                ("pita".to_owned(), 20.5),
+                ("pie".to_owned(), 5.0),
+                ("pizza".to_owned(), 0.5)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_all() {
+        let mut trie = WeightedTrie::with_max_word_len(4);
+        let items: Vec<WeightedString> = ["pie", "pizza", "", "pita"]
+            .iter()
+            .map(|word| WeightedString {
+                word: (*word).to_owned(),
+                weight: 1,
+            })
+            .collect();
+        assert_eq!(trie.insert_all(items), vec![true, false, false, true]);
+        assert_eq!(trie.search("pi"), vec!["pie", "pita"]);
+    }
+
+    #[test]
+    fn test_prefix_count_trail() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pop".to_owned(), 3);
+        assert_eq!(trie.prefix_count_trail("piz"), vec![5, 4, 1]);
+        assert_eq!(trie.prefix_count_trail("pix"), vec![5, 4]);
+        assert!(trie.prefix_count_trail("").is_empty());
+    }
+
+    #[test]
+    fn test_with_depth_scaled_cap() {
+        let mut trie = WeightedTrie::with_depth_scaled_cap(6);
+        let mut full = WeightedTrie::new();
+        for (word, weight) in [
+            ("pa", 1),
+            ("pb", 2),
+            ("pca", 3),
+            ("pcb", 4),
+            ("pcc", 5),
+            ("pcd", 6),
+            ("pce", 7),
+        ] {
+            trie.insert(word.to_owned(), weight);
+            full.insert(word.to_owned(), weight);
+        }
+        // depth 1 keeps up to 6, depth 2 up to 3
+        assert_eq!(trie.search("p").len(), 6);
+        assert_eq!(trie.search("pc").len(), 3);
+        assert_eq!(trie.search("pcc").len(), 1);
+        assert_eq!(trie.search("p"), full.search("p")[..6]);
+        assert_eq!(trie.search("pc"), full.search("pc")[..3]);
+
+        let mut built = WeightedTrie::builder().depth_scaled_cap(2).build();
+        for word in ["pa", "pb", "pc"] {
+            built.insert(word.to_owned(), 1);
+        }
+        assert_eq!(built.search("p"), vec!["pa", "pb"]);
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_search_cached() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        assert_eq!(trie.search_cached("pi", 1), vec!["pie"]);
+        assert_eq!(trie.search_cached("pi", 1), vec!["pie"]);
+        assert_eq!(trie.search_cached("pi", 5), vec!["pie", "pita"]);
+
+        trie.insert("pizza".to_owned(), 10);
+        assert_eq!(trie.search_cached("pi", 1), vec!["pizza"]);
+        trie.update_weights(&[("pita", 20)]);
+        assert_eq!(trie.search_cached("pi", 1), vec!["pita"]);
+        trie.prune_to_top(1);
+        assert!(trie.search_cached("pie", 1).is_empty());
+    }
+
+    #[test]
+    fn test_search_in_category() {
+        const FOOD: u16 = 1;
+        const PLACE: u16 = 2;
+
+        let mut trie = WeightedTrie::new();
+        trie.insert_categorized("pizza".to_owned(), 10, FOOD)
+            .unwrap();
+        trie.insert_categorized("paris".to_owned(), 8, PLACE)
+            .unwrap();
+        trie.insert_categorized("pie".to_owned(), 5, FOOD).unwrap();
+        trie.insert("pen".to_owned(), 20);
+        assert_eq!(trie.search_in_category("p", FOOD, 5), vec!["pizza", "pie"]);
+        assert_eq!(trie.search_in_category("p", FOOD, 1), vec!["pizza"]);
+        assert_eq!(trie.search_in_category("p", PLACE, 5), vec!["paris"]);
+        assert!(trie.search_in_category("pi", PLACE, 5).is_empty());
+        assert_eq!(trie.search("p"), vec!["pen", "pizza", "paris", "pie"]);
+    }
+
+    #[test]
+    fn test_search_exhaustive() {
+        let mut trie = WeightedTrie::with_max_suggestions_per_node(2);
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie"]);
+        assert_eq!(
+            trie.search_exhaustive("pi"),
+            vec![
+                ("pizza".to_owned(), 10),
+                ("pie".to_owned(), 5),
+                ("pita".to_owned(), 2)
+            ]
+        );
+        // "pi" only ever had its own, already full, node to go to
+        assert!(!trie.search_exhaustive("p").iter().any(|(w, _)| w == "pi"));
+        assert_eq!(trie.search_exhaustive("pit"), vec![("pita".to_owned(), 2)]);
+        assert!(trie.search_exhaustive("x").is_empty());
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_with_nfc() {
+        let decomposed = "cafe\u{301}";
+        let mut trie = WeightedTrie::with_nfc();
+        trie.insert(decomposed.to_owned(), 3);
+        trie.insert("café".to_owned(), 1);
+        assert_eq!(trie.search("caf"), vec!["café", "café"]);
+        assert_eq!(trie.search("café"), vec!["café", "café"]);
+        assert_eq!(trie.search(decomposed), vec!["café", "café"]);
+
+        let mut plain = WeightedTrie::new();
+        plain.insert(decomposed.to_owned(), 3);
+        assert!(plain.search("café").is_empty());
+
+        let mut built = WeightedTrie::builder().nfc(true).build();
+        built.insert(decomposed.to_owned(), 3);
+        assert_eq!(built.search("café"), vec!["café"]);
+    }
+
+    #[test]
+    fn test_max_suggestions_keeps_heaviest() {
+        let weights = [4, 9, 1, 7, 3, 8, 2, 6, 5];
+        let mut trie = WeightedTrie::with_max_suggestions_per_node(3);
+        for (i, weight) in weights.iter().enumerate() {
+            trie.insert(format!("p{i}"), *weight);
+            assert!(trie.root().child('p').unwrap().suggestions().len() <= 3);
+        }
+        assert_eq!(trie.search("p"), vec!["p1", "p5", "p3"]);
+    }
+
+    #[test]
+    fn test_descendants() {
+        let mut trie = WeightedTrie::with_max_suggestions_per_node(1);
+        for (word, weight) in [("pie", 5), ("pita", 2), ("pi", 1), ("pizza", 10), ("ox", 3)] {
+            trie.insert(word.to_owned(), weight);
+        }
+        let mut words: Vec<String> = trie.descendants("pi").collect();
+        words.sort();
+        assert_eq!(words, vec!["pi", "pie", "pita", "pizza"]);
+        assert_eq!(trie.descendants("").count(), 5);
+        assert_eq!(trie.descendants("pizza").collect::<Vec<_>>(), vec!["pizza"]);
+        assert_eq!(trie.descendants("x").count(), 0);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "suggestions out of order")]
+    fn test_debug_assert_sorted_catches_corruption() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+        let mut p = trie.root_mut().child_mut('p').unwrap();
+        p.suggestions_raw_mut().reverse();
+        trie.insert("pita".to_owned(), 7);
+    }
+
+    #[test]
+    fn test_prefix_search_trait() {
+        fn top_words(index: &impl PrefixSearch, prefix: &str) -> Vec<String> {
+            index
+                .search_top_k(prefix, 2)
+                .into_iter()
+                .map(|(word, _)| word)
+                .collect()
+        }
+
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pizza".to_owned(), 10);
+        let bytes = trie.to_bytes();
+        let view = SearchableBytes::from_bytes(&bytes).unwrap();
+        assert_eq!(top_words(&trie, "pi"), vec!["pizza", "pie"]);
+        assert_eq!(view.search_top_k("pi", 5), trie.search_top_k("pi", 5));
+        assert_eq!(top_words(&view, "pit"), vec!["pita"]);
+        let frozen = trie.freeze();
+        assert_eq!(top_words(&frozen, "pi"), vec!["pizza", "pie"]);
+        assert!(top_words(&frozen, "x").is_empty());
+    }
+
+    #[test]
+    fn test_lazy_trie_matches_weighted_trie() {
+        let syllables = ["pi", "za", "e", "ta", "p", "o"];
+        let mut weighted_strings = Vec::new();
+        for i in 0..300usize {
+            let word: String = (0..1 + i % 4)
+                .map(|j| syllables[(i * 7 + j * 3) % syllables.len()])
+                .collect();
+            weighted_strings.push(WeightedString {
+                word,
+                weight: (i * 37 % 11) as i32 - 5,
+            });
+        }
+        let eager = WeightedTrie::build(weighted_strings.clone());
+        let lazy = LazyTrie::build(weighted_strings);
+        for prefix in ["", "p", "pi", "piza", "e", "ta", "o", "op", "x"] {
+            for k in [0, 1, 3, 10, 1000] {
+                assert_eq!(
+                    lazy.search_top_k(prefix, k),
+                    eager.search_top_k(prefix, k),
+                    "{prefix:?} {k}"
+                );
+            }
+            assert_eq!(lazy.search(prefix), eager.search(prefix));
+        }
+    }
+
+    #[test]
+    fn test_get() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert_with_id("pizza".to_owned(), 10, 42).unwrap();
+        trie.insert_categorized("pita".to_owned(), 2, 3).unwrap();
+        assert_eq!(
+            trie.get("pizza"),
+            Some(WordEntry {
+                weight: 10,
+                id: Some(42),
+                category: None,
+            })
+        );
+        assert_eq!(
+            trie.get("pie"),
+            Some(WordEntry {
+                weight: 5,
+                id: None,
+                category: None,
+            })
+        );
+        assert_eq!(trie.get("pita").unwrap().category, Some(3));
+        assert_eq!(trie.get("nope"), None);
+        assert_eq!(trie.get("pi"), None);
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let mut trie = WeightedTrie::new();
+        trie.insert_ns("products", "pizza oven".to_owned(), 3);
+        trie.insert_ns("products", "pie dish".to_owned(), 5);
+        trie.insert_ns("queries", "pizza near me".to_owned(), 10);
+        trie.insert_ns("prod", "pizza".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 7);
+
+        assert_eq!(
+            trie.search_ns("products", "pi"),
+            vec!["pie dish", "pizza oven"]
+        );
+        assert_eq!(trie.search_ns("queries", "pi"), vec!["pizza near me"]);
+        assert_eq!(trie.search_ns("prod", "pi"), vec!["pizza"]);
+        assert_eq!(trie.search_ns("queries", ""), vec!["pizza near me"]);
+        assert!(trie.search_ns("other", "pi").is_empty());
+        assert_eq!(trie.search("pi"), vec!["pizza"]);
+    }
+
+    #[test]
+    fn test_tie_break_shorter_first() {
+        let words = [
+            ("pizza", 5),
+            ("pita", 5),
+            ("pie", 5),
+            ("pitas", 9),
+            ("pi", 1),
+        ];
+        let mut trie = WeightedTrie::with_tie_break(TieBreak::ShorterFirst);
+        let mut default = WeightedTrie::new();
+        for (word, weight) in words {
+            trie.insert(word.to_owned(), weight);
+            default.insert(word.to_owned(), weight);
+        }
+        assert_eq!(
+            trie.search("pi"),
+            vec!["pitas", "pie", "pita", "pizza", "pi"]
+        );
+        assert_eq!(
+            default.search("pi"),
+            vec!["pitas", "pizza", "pita", "pie", "pi"]
+        );
+
+        let mut built = WeightedTrie::builder()
+            .tie_break(TieBreak::ShorterFirst)
+            .build();
+        built.insert("pizza".to_owned(), 5);
+        built.insert("pie".to_owned(), 5);
+        assert_eq!(built.search("pi"), vec!["pie", "pizza"]);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pizza".to_owned(), 10);
+
+        let report = trie.evaluate(&[
+            ("pi", "pizza"),
+            ("pi", "pie"),
+            ("p", "pita"),
+            ("pi", "pasta"),
+        ]);
+        assert_eq!(
+            report,
+            EvalReport {
+                ranks: vec![Some(0), Some(1), Some(2), None],
+            }
+        );
+        let mrr = (1.0 + 1.0 / 2.0 + 1.0 / 3.0) / 4.0;
+        assert!((report.mean_reciprocal_rank() - mrr).abs() < 1e-12);
+        assert_eq!(report.hit_at(1), 0.25);
+        assert_eq!(report.hit_at(2), 0.5);
+        assert_eq!(trie.evaluate(&[]).mean_reciprocal_rank(), 0.0);
+    }
+
+    #[test]
+    fn test_record_selection() {
+        let mut trie = WeightedTrie::with_selection_increment(2);
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pizza".to_owned(), 6);
+
+        assert_eq!(trie.record_selection("pi", "pita"), Some(2));
+        assert_eq!(trie.record_selection("pi", "pita"), Some(1));
+        assert_eq!(trie.record_selection("pi", "pita"), Some(0));
+        assert_eq!(trie.search("pi"), vec!["pita", "pizza", "pie"]);
+
+        assert_eq!(trie.record_selection("pi", "pistachio"), Some(3));
+        assert_eq!(trie.best("pis"), Some(("pistachio".to_owned(), 2)));
+
+        let mut trie = WeightedTrie::new();
+        trie.record_selection("p", "pie");
+        assert_eq!(trie.best("p"), Some(("pie".to_owned(), 1)));
+    }
+
+    #[test]
+    fn test_prefixes_with_min_completions() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("ox".to_owned(), 3);
+        assert_eq!(trie.prefixes_with_min_completions(3), vec!["p", "pi"]);
+        assert_eq!(trie.prefixes_with_min_completions(5), Vec::<String>::new());
+        assert_eq!(trie.prefixes_with_min_completions(1).len(), 10);
+    }
+
+    #[test]
+    fn test_search_highlighted() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pie".to_owned(), 5);
+        assert_eq!(
+            trie.search_highlighted("pi", 5),
+            vec![("pizza".to_owned(), 0..2), ("pie".to_owned(), 0..2)]
+        );
+
+        let mut trie = WeightedTrie::with_case_insensitive();
+        trie.insert("Pizza".to_owned(), 10);
+        trie.insert("İstanbul".to_owned(), 5);
+        assert_eq!(
+            trie.search_highlighted("PI", 5),
+            vec![("Pizza".to_owned(), 0..2)]
+        );
+        // "İ" is 2 bytes but folds to the 3 byte "i̇".
+        assert_eq!(
+            trie.search_highlighted("i̇s", 5),
+            vec![("İstanbul".to_owned(), 0..3)]
+        );
+        assert!(trie.search_highlighted("x", 5).is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_export_apply_weights() {
+        let build = || {
+            let mut trie = WeightedTrie::new();
+            for (i, word) in ["pie", "pita", "pi", "pizza"].iter().enumerate() {
+                trie.insert(word.to_string(), i as i32);
+            }
+            trie
+        };
+        let mut master = build();
+        master.update_weights(&[("pie", 10), ("pi", -5)]);
+        master.insert("pilaf".to_owned(), 4);
+
+        let mut ignoring = build();
+        ignoring.apply_weights(&master.export_weights(), false);
+        assert_eq!(ignoring.search("pi"), vec!["pie", "pizza", "pita", "pi"]);
+
+        let mut replica = build();
+        replica.apply_weights(&master.export_weights(), true);
+        assert_eq!(replica.search("pi"), master.search("pi"));
+        assert_eq!(replica.export_weights(), master.export_weights());
+    }
+
+    #[test]
+    fn test_max_children_per_node() {
+        let mut trie = WeightedTrie::with_max_children_per_node(2);
+        assert_eq!(trie.try_insert("pa".to_owned(), 1), Ok(()));
+        assert_eq!(trie.try_insert("pb".to_owned(), 2), Ok(()));
+        assert_eq!(
+            trie.try_insert("pc".to_owned(), 3),
+            Err(TrieError::TooManyChildren { max: 2 })
+        );
+        // existing branches and a new root child are still allowed
+        assert_eq!(trie.try_insert("pa".to_owned(), 4), Ok(()));
+        assert_eq!(trie.try_insert("qxyz".to_owned(), 5), Ok(()));
+        assert_eq!(
+            trie.try_insert("r".to_owned(), 6),
+            Err(TrieError::TooManyChildren { max: 2 })
+        );
+        trie.insert_chars(&['p', 'd'], "pd", 7);
+        assert_eq!(trie.search("p"), vec!["pa", "pb", "pa"]);
+        assert!(trie.search("pc").is_empty());
+        assert!(trie.search("r").is_empty());
+        assert!(!trie.contains("pd"));
+    }
+
+    #[test]
+    fn test_search_top_k_past_cap() {
+        let mut trie = WeightedTrie::with_max_suggestions_per_node(2);
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pilaf".to_owned(), 1);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie"]);
+        assert_eq!(
+            trie.search_top_k("pi", 2),
+            vec![("pizza".to_owned(), 10), ("pie".to_owned(), 5)]
+        );
+        assert_eq!(
+            trie.search_top_k("pi", 3),
+            vec![
+                ("pizza".to_owned(), 10),
+                ("pie".to_owned(), 5),
+                ("pita".to_owned(), 2),
+            ]
+        );
+        assert_eq!(trie.search_top_k("pi", 10).len(), 4);
+
+        // a node below its cap is trusted, so a word missing from its list
+        // isn't dug up by a walk of the subtree
+        let mut small = WeightedTrie::with_max_suggestions_per_node(3);
+        small.insert("pie".to_owned(), 5);
+        small.insert("pita".to_owned(), 2);
+        let mut p = small.root_mut().child_mut('p').unwrap();
+        p.suggestions_raw_mut().pop();
+        assert_eq!(small.search_top_k("p", 3), vec![("pie".to_owned(), 5)]);
+    }
+
+    #[test]
+    fn test_words_sorted_path_buffer() {
+        let mut trie = WeightedTrie::with_case_insensitive();
+        for (word, weight) in [
+            ("Pizza", 10),
+            ("pi", 1),
+            ("pie", 5),
+            ("ox", 3),
+            ("café", 2),
+            ("cafe", 4),
+            ("PIE", 7),
+        ] {
+            trie.insert(word.to_owned(), weight);
+        }
+        // sorted by key, then by spelling among words sharing a key
+        assert_eq!(
+            trie.words_sorted(),
+            vec!["cafe", "café", "ox", "pi", "PIE", "pie", "Pizza"]
+        );
+        let mut descendants: Vec<String> = trie.descendants("PI").collect();
+        descendants.sort();
+        assert_eq!(descendants, vec!["pi", "pie", "pizza"]);
+        assert_eq!(trie.descendants("x").count(), 0);
+    }
+
+    #[test]
+    fn test_tie_break_recency_first() {
+        let mut trie = WeightedTrie::with_tie_break(TieBreak::RecencyFirst);
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 5);
+        trie.insert("pi".to_owned(), 1);
+        assert_eq!(trie.search("pi"), vec!["pizza", "pita", "pie", "pi"]);
+
+        // an update refreshes the word's recency
+        trie.update_weights(&[("pie", 0)]);
+        assert_eq!(trie.search("pi"), vec!["pie", "pizza", "pita", "pi"]);
+        assert!(trie.validate().is_empty());
+
+        let mut built = WeightedTrie::builder()
+            .tie_break(TieBreak::RecencyFirst)
+            .build();
+        built.insert("a".to_owned(), 2);
+        built.insert("ab".to_owned(), 2);
+        assert_eq!(built.search("a"), vec!["ab", "a"]);
+    }
+
+    #[test]
+    fn test_popular() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [
+            ("pie", 5),
+            ("pita", 2),
+            ("ox", 7),
+            ("pizza", 10),
+            ("apple", 1),
+        ] {
+            trie.insert(word.to_owned(), weight);
+        }
+        let top = |words: &[(&str, i32)]| -> Vec<(String, i32)> {
+            words
+                .iter()
+                .map(|&(w, weight)| (w.to_owned(), weight))
+                .collect()
+        };
+        assert_eq!(
+            trie.popular(3),
+            top(&[("pizza", 10), ("ox", 7), ("pie", 5)])
+        );
+        // served from the memoized list
+        assert_eq!(trie.popular(2), top(&[("pizza", 10), ("ox", 7)]));
+
+        trie.insert("banana".to_owned(), 20);
+        assert_eq!(
+            trie.popular(3),
+            top(&[("banana", 20), ("pizza", 10), ("ox", 7)])
+        );
+        assert_eq!(trie.popular(10).len(), 6);
+    }
+
+    #[test]
+    fn test_frozen_from_sorted() {
+        let mut words = vec![
+            ("pi", 1),
+            ("pie", 5),
+            ("piece", 5),
+            ("pita", 2),
+            ("pizza", 10),
+            ("pizza", 3),
+            ("ox", 5),
+            ("oxen", 7),
+            ("café", 4),
+            ("cafe", 4),
+            ("b", -2),
+        ];
+        words.sort();
+        let weighted = || {
+            words.iter().map(|&(word, weight)| WeightedString {
+                word: word.to_owned(),
+                weight,
+            })
+        };
+        let frozen = FrozenTrie::from_sorted(weighted());
+        let trie = WeightedTrie::build(weighted().collect());
+        for prefix in [
+            "", "p", "pi", "pie", "piz", "pizza", "o", "oxe", "c", "caf", "café", "b", "x",
+        ] {
+            assert_eq!(
+                frozen.search(prefix),
+                trie.search(prefix),
+                "prefix {prefix:?}"
+            );
+        }
+        let bytes = frozen.to_bytes();
+        let searchable = SearchableBytes::from_bytes(&bytes).unwrap();
+        assert_eq!(searchable.search("pi"), trie.search("pi"));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut trie = WeightedTrie::with_metrics();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+        trie.search("pi");
+        trie.search("piz");
+        trie.search("apple");
+        let metrics = trie.metrics();
+        assert_eq!(
+            metrics,
+            QueryMetrics {
+                queries: 3,
+                zero_results: 1,
+                results: 3,
+            }
+        );
+        assert_eq!(metrics.average_results(), 1.0);
+
+        let mut plain = WeightedTrie::new();
+        plain.insert("pie".to_owned(), 5);
+        plain.search("pi");
+        assert_eq!(plain.metrics(), QueryMetrics::default());
+        let built = WeightedTrie::builder().metrics(true).build();
+        built.search("x");
+        assert_eq!(built.metrics().zero_results, 1);
+    }
+
+    #[test]
+    fn test_search_extensions() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("pizza".to_owned(), 10);
+        assert_eq!(
+            trie.search_extensions("pi", 10),
+            vec!["pizza", "pie", "pita"]
+        );
+        assert_eq!(trie.search_extensions("pi", 1), vec!["pizza"]);
+        assert!(trie.search_extensions("pizza", 10).is_empty());
+
+        let mut folded = WeightedTrie::with_case_insensitive();
+        folded.insert("Pi".to_owned(), 20);
+        folded.insert("Pie".to_owned(), 5);
+        assert_eq!(folded.search_extensions("pI", 10), vec!["Pie"]);
+    }
+
+    #[test]
+    fn test_build_reporting() {
+        let items = ["pie", "pizza", "pie", "", "pizza", "pie", "pita"]
+            .iter()
+            .zip([5, 10, 1, 3, 2, 1, 4])
+            .map(|(word, weight)| WeightedString {
+                word: word.to_string(),
+                weight,
+            })
+            .collect();
+        let (trie, report) = WeightedTrie::build_reporting(items);
+        assert_eq!(
+            report,
+            BuildReport {
+                inputs: 7,
+                distinct: 3,
+                duplicates: 3,
+                rejected: vec![(
+                    WeightedString {
+                        word: "".to_owned(),
+                        weight: 3,
+                    },
+                    TrieError::EmptyWord
+                )],
+            }
+        );
+        assert_eq!(
+            trie.search_top_k("pi", 5),
+            vec![
+                ("pizza".to_owned(), 12),
+                ("pie".to_owned(), 7),
+                ("pita".to_owned(), 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_has_completions() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        assert!(trie.has_completions("pi"));
+        assert!(!trie.has_completions("pix"));
+
+        // membership only: the paths exist but keep no suggestions
+        let mut members = WeightedTrie::with_max_suggestions_per_node(0);
+        members.insert("pie".to_owned(), 5);
+        assert!(members.contains("pie"));
+        assert!(members.contains_prefix("pi"));
+        assert!(!members.has_completions("pi"));
+        assert!(!members.has_completions("pix"));
+    }
+
+    #[test]
+    fn test_rank_candidates() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pita".to_owned(), -1);
+        let candidates = ["pita", "pasta", "pizza", "pie", "pi"];
+        assert_eq!(
+            trie.rank_candidates(&candidates, false),
+            vec![
+                ("pizza".to_owned(), 10),
+                ("pie".to_owned(), 5),
+                ("pita".to_owned(), -1)
+            ]
+        );
+        assert_eq!(
+            trie.rank_candidates(&candidates, true),
+            vec![
+                ("pizza".to_owned(), 10),
+                ("pie".to_owned(), 5),
+                ("pasta".to_owned(), 0),
+                ("pi".to_owned(), 0),
+                ("pita".to_owned(), -1)
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_ahead_log_replay() {
+        let mut wal = WriteAheadLog::new(WeightedTrie::new(), Vec::new());
+        wal.insert("pie".to_owned(), 5).unwrap();
+        wal.insert("pizza".to_owned(), 10).unwrap();
+        wal.insert("pita".to_owned(), 2).unwrap();
+        wal.insert("a\tb".to_owned(), 1).unwrap();
+        wal.update_weight("pita", 20).unwrap();
+        assert!(wal.remove("pie").unwrap());
+        assert!(!wal.remove("pear").unwrap());
+        assert!(wal.insert("x\ny".to_owned(), 1).is_err());
+        assert_eq!(wal.trie().search("pi"), vec!["pita", "pizza"]);
+
+        let (trie, log) = wal.into_parts();
+        let replayed = WeightedTrie::replay(log.as_slice()).unwrap();
+        assert!(replayed == trie);
+        assert_eq!(replayed.search("a"), vec!["a\tb"]);
+
+        let corrupt = WeightedTrie::replay("insert\tfive\tpie\n".as_bytes());
+        assert_eq!(corrupt.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_search_paginated_stable_id() {
+        let words = [
+            ("pie", 5),
+            ("pita", 5),
+            ("pizza", 5),
+            ("pilaf", 5),
+            ("pi", 9),
+            ("pit", 1),
+        ];
+        let mut forward = WeightedTrie::with_tie_break(TieBreak::StableId);
+        for (word, weight) in words {
+            forward.insert(word.to_owned(), weight);
+        }
+        let mut backward = WeightedTrie::builder()
+            .tie_break(TieBreak::StableId)
+            .build();
+        for (word, weight) in words.iter().rev() {
+            backward.insert(word.to_string(), *weight);
+        }
+        for page in 0..4 {
+            assert_eq!(
+                forward.search_paginated("pi", page, 2),
+                backward.search_paginated("pi", page, 2)
+            );
+        }
+        assert_eq!(forward.search_paginated("pi", 0, 1), vec!["pi"]);
+        assert_eq!(forward.search_paginated("pi", 2, 2)[1], "pit");
+        assert!(forward.search_paginated("pi", 3, 2).is_empty());
+
+        // the id is a fixed FNV-1a hash
+        assert_eq!(stable_id(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_id("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_lines_streaming() {
+        let data = "pie\t5\npita\t2\r\n\npi\t1\npizza\t10\na\tb\t3\n";
+        let trie = WeightedTrie::from_lines_streaming(std::io::Cursor::new(data)).unwrap();
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie", "pita", "pi"]);
+        assert_eq!(trie.search("a"), vec!["a\tb"]);
+
+        let mut tsv = Vec::new();
+        trie.write_tsv(&mut tsv, TsvOrder::Weight).unwrap();
+        let reread = WeightedTrie::from_lines_streaming(tsv.as_slice()).unwrap();
+        assert_eq!(reread.search("pi"), trie.search("pi"));
+
+        let bad = WeightedTrie::from_lines_streaming(std::io::Cursor::new("pie\n"));
+        assert_eq!(bad.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_longest_matching_prefix() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pie".to_owned(), 5);
+        assert_eq!(
+            trie.longest_matching_prefix("pizzeria"),
+            ("pizz".to_owned(), vec!["pizza".to_owned()])
+        );
+        assert_eq!(
+            trie.longest_matching_prefix("pix"),
+            ("pi".to_owned(), vec!["pizza".to_owned(), "pie".to_owned()])
+        );
+        assert_eq!(
+            trie.longest_matching_prefix("pie"),
+            ("pie".to_owned(), vec!["pie".to_owned()])
+        );
+        assert_eq!(trie.longest_matching_prefix("x"), (String::new(), vec![]));
+    }
+
+    #[test]
+    fn test_collapsed_whitespace() {
+        let mut trie = WeightedTrie::with_collapsed_whitespace();
+        trie.insert("new york".to_owned(), 5);
+        trie.insert("new\t\tyork city".to_owned(), 2);
+        assert_eq!(trie.search("new   y"), vec!["new york", "new york city"]);
+        assert_eq!(trie.get("new \t york").map(|entry| entry.weight), Some(5));
+
+        // with deduplication the spellings are one entry
+        let mut merged = WeightedTrie::builder()
+            .collapse_whitespace(true)
+            .dedup_policy(DedupPolicy::Exact)
+            .build();
+        merged.insert("new york".to_owned(), 5);
+        merged.insert("new  york".to_owned(), 3);
+        assert_eq!(
+            merged.search_top_k("new", 5),
+            vec![("new york".to_owned(), 8)]
+        );
+
+        let mut keyed = WeightedTrie::builder().collapse_whitespace(true).build();
+        keyed
+            .insert_keyed("los   angeles", "Los   Angeles".to_owned(), 1)
+            .unwrap();
+        assert_eq!(keyed.search("los a"), vec!["Los   Angeles"]);
+        // the edges are left alone
+        assert!(keyed.search(" los").is_empty());
+    }
+
+    #[test]
+    fn test_estimate_cap_savings() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pita".to_owned(), 2);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("ox".to_owned(), 3);
+        // "p" and "pi" each drop "pie" and "pita" at a cap of 1
+        let entry = std::mem::size_of::<(i32, String)>();
+        let expected = 2 * (entry + "pie".len() + entry + "pita".len());
+        assert_eq!(trie.estimate_cap_savings(1), expected);
+        assert_eq!(trie.estimate_cap_savings(3), 0);
+
+        let before = trie.estimated_memory_bytes();
+        trie.truncate_suggestions(1);
+        assert_eq!(before - trie.estimated_memory_bytes(), expected);
+    }
+
+    #[test]
+    fn test_min_query_len() {
+        let mut trie = WeightedTrie::with_min_query_len(2);
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+        assert!(trie.search("p").is_empty());
+        assert!(trie.search("").is_empty());
+        assert!(trie.search_top_k("p", 5).is_empty());
+        assert_eq!(trie.search("pi"), vec!["pizza", "pie"]);
+
+        let mut built = WeightedTrie::builder().min_query_len(2).build();
+        built.insert("é".to_owned(), 1);
+        built.insert("éa".to_owned(), 1);
+        // counted in chars, not bytes
+        assert!(built.search("é").is_empty());
+        assert_eq!(built.search("éa"), vec!["éa"]);
+    }
+
+    #[test]
+    fn test_bfs() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pi".to_owned(), 1);
+        trie.insert("ox".to_owned(), 3);
+        let nodes: Vec<(String, usize, usize)> = trie.bfs().collect();
+        let expected = [
+            ("o", 1, 1),
+            ("p", 1, 2),
+            ("ox", 2, 1),
+            ("pi", 2, 2),
+            ("pie", 3, 1),
+        ];
+        assert_eq!(
+            nodes,
+            expected
+                .iter()
+                .map(|&(prefix, depth, count)| (prefix.to_owned(), depth, count))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(WeightedTrie::new().bfs().count(), 0);
+    }
+
+    #[test]
+    fn test_insert_replace() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        assert_eq!(trie.insert_replace("pizza".to_owned(), 1), None);
+        assert_eq!(trie.insert_replace("pizza".to_owned(), 10), Some(1));
+        assert_eq!(
+            trie.search_top_k("pi", 5),
+            vec![("pizza".to_owned(), 10), ("pie".to_owned(), 5)]
+        );
+        assert_eq!(trie.search("pizza"), vec!["pizza"]);
+    }
+
+    #[test]
+    fn test_smart_search() {
+        let mut trie = WeightedTrie::new();
+        for (word, weight) in [
+            ("pie", 5),
+            ("piece", 4),
+            ("pit", 3),
+            ("pine", 2),
+            ("pizza", 10),
+            ("ox", 1),
+        ] {
+            trie.insert(word.to_owned(), weight);
+        }
+        let pairs = |words: &[(&str, i32)]| -> Vec<(String, i32)> {
+            words
+                .iter()
+                .map(|&(w, weight)| (w.to_owned(), weight))
+                .collect()
+        };
+        // enough completions, no fuzzy pass
+        assert_eq!(
+            trie.smart_search("pi", 2),
+            pairs(&[("pizza", 10), ("pie", 5)])
+        );
+        // no completions, only the fuzzy matches of a typo
+        assert_eq!(trie.smart_search("pizzq", 3), pairs(&[("pizza", 10)]));
+        // completions first, then words one edit away
+        assert_eq!(
+            trie.smart_search("pie", 4),
+            pairs(&[("pie", 5), ("piece", 4), ("pit", 3), ("pine", 2)])
+        );
+        // too short for edits
+        assert!(trie.smart_search("px", 3).is_empty());
+    }
+
+    #[test]
+    fn test_rebuild_suggestions_keeps_keyed_words() {
+        let mut trie = WeightedTrie::with_max_word_len(8);
+        trie.insert_keyed("new-york", "New York".to_owned(), 5)
+            .unwrap();
+        trie.insert_keyed("la", "Los Angeles, California".to_owned(), 3)
+            .unwrap();
+        trie.rebuild_suggestions();
+        assert_eq!(trie.search("new-"), vec!["New York"]);
+        assert!(trie.search("New").is_empty());
+        assert_eq!(trie.search("l"), vec!["Los Angeles, California"]);
+    }
+
+    #[test]
+    fn test_retain_keeps_membership_only_words() {
+        let build = || {
+            let mut trie = WeightedTrie::with_max_suggestions_per_node(0);
+            trie.insert("pizza".to_owned(), 10);
+            trie.insert("pie".to_owned(), 5);
+            trie
+        };
+        #[cfg(feature = "std")]
+        {
+            let mut trie = build();
+            let nodes = trie.node_count();
+            trie.expire(std::time::Instant::now());
+            assert!(trie.contains("pizza"));
+            assert_eq!(trie.node_count(), nodes);
+        }
+
+        let mut trie = build();
+        trie.prune_to_top(5);
+        assert!(trie.contains("pie"));
+        let mut trie = build();
+        trie.prune_to_coverage(1.0);
+        assert!(trie.contains("pizza"));
+    }
+
+    #[test]
+    fn test_capped_trie_keeps_words_out_of_first_char_list() {
+        let build = || {
+            let mut trie = WeightedTrie::with_max_suggestions_per_node(1);
+            trie.insert("pizza".to_owned(), 10);
+            trie.insert("pie".to_owned(), 5);
+            trie
+        };
+        let trie = build();
+        assert_eq!(trie.search("p"), vec!["pizza".to_owned()]);
+        #[cfg(feature = "std")]
+        {
+            assert_eq!(trie.export_weights().get("pie"), Some(&5));
+            let mut tsv = Vec::new();
+            trie.write_tsv(&mut tsv, TsvOrder::Word).unwrap();
+            assert_eq!(String::from_utf8(tsv).unwrap(), "pie\t5\npizza\t10\n");
+        }
+        assert!(trie.diff(&build()).added.is_empty());
+        assert!(trie.validate().is_empty());
+
+        let mut merged = WeightedTrie::new();
+        merged.merge_with(build(), MergePolicy::Sum);
+        assert_eq!(merged.search("pie"), vec!["pie".to_owned()]);
+
+        let mut pruned = build();
+        pruned.prune_to_top(3);
+        assert!(pruned.contains("pie"));
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_side_maps_use_nfc_spelling() {
+        let mut trie = WeightedTrie::with_nfc();
+        trie.insert_categorized("cafe\u{301}".to_owned(), 3, 7)
+            .unwrap();
+        assert_eq!(trie.search_in_category("caf", 7, 5), vec!["café"]);
+        assert_eq!(trie.get("café").unwrap().category, Some(7));
+    }
+
+    #[test]
+    fn test_insert_with_id_replaces_deduped_id() {
+        let mut trie = WeightedTrie::with_dedup_policy(DedupPolicy::CaseInsensitive);
+        trie.insert_with_id("Pizza".to_owned(), 1, 1).unwrap();
+        trie.insert_with_id("pizza".to_owned(), 1, 2).unwrap();
+        assert_eq!(trie.get("Pizza").unwrap().id, Some(2));
+        assert_eq!(trie.search_ids("Piz"), vec![2]);
+    }
+
+    #[test]
+    fn test_recency_first_survives_rebuild() {
+        let mut trie = WeightedTrie::with_tie_break(TieBreak::RecencyFirst);
+        for word in ["pa", "pb", "pc"] {
+            trie.insert(word.to_owned(), 1);
+        }
+        trie.rebuild_suggestions();
+        assert_eq!(trie.search("p"), vec!["pc", "pb", "pa"]);
+
+        trie.update_weights(&[("pa", 0)]);
+        trie.rebuild_suggestions();
+        assert_eq!(trie.search("p"), vec!["pa", "pc", "pb"]);
+    }
+
+    #[test]
+    fn test_frozen_trie_keeps_key_options() {
+        let mut trie = WeightedTrie::builder()
+            .trim(true)
+            .collapse_whitespace(true)
+            .min_query_len(2)
+            .build();
+        trie.insert(" pizza  place ".to_owned(), 3);
+        let bytes = trie.to_bytes();
+        let view = SearchableBytes::from_bytes(&bytes).unwrap();
+        let frozen = trie.freeze();
+        for prefix in [" pi", "pizza   p"] {
+            assert_eq!(frozen.search(prefix), vec!["pizza place"], "{prefix}");
+            assert_eq!(view.search(prefix), vec!["pizza place"], "{prefix}");
+        }
+        assert!(frozen.search("p").is_empty());
+        assert!(view.search("p").is_empty());
+    }
+
+    #[test]
+    fn test_words_sorted_keeps_folded_spellings() {
+        let mut trie = WeightedTrie::with_case_insensitive();
+        trie.insert("Pizza".to_owned(), 1);
+        trie.insert("PIZZA".to_owned(), 2);
+        trie.insert("pie".to_owned(), 3);
+        trie.insert("pie".to_owned(), 4);
+        assert_eq!(trie.words_sorted(), vec!["pie", "PIZZA", "Pizza"]);
+    }
+
+    #[test]
+    fn test_weights_histogram_counts_words_once() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 6);
+        assert_eq!(trie.weights_histogram(1), vec![(5, 6, 2)]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_replay_into_configured_trie() {
+        let mut wal = WriteAheadLog::new(WeightedTrie::with_case_insensitive(), Vec::new());
+        wal.insert("Pizza".to_owned(), 5).unwrap();
+        let (_, log) = wal.into_parts();
+
+        let mut trie = WeightedTrie::with_case_insensitive();
+        trie.replay_into(log.as_slice()).unwrap();
+        assert_eq!(trie.search("piz"), vec!["Pizza"]);
+        assert!(WeightedTrie::replay(log.as_slice())
+            .unwrap()
+            .search("piz")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_insert_keyed_checks_limits() {
+        let mut trie = WeightedTrie::with_max_children_per_node(1);
+        trie.insert_keyed("ab", "AB".to_owned(), 1).unwrap();
+        assert_eq!(
+            trie.insert_keyed("ad", "AD".to_owned(), 1),
+            Err(TrieError::TooManyChildren { max: 1 })
+        );
+        assert!(trie.search("a").iter().all(|word| word == "AB"));
+
+        let mut trie = WeightedTrie::with_max_word_len(2);
+        assert_eq!(
+            trie.insert_keyed("abcdef", "A".to_owned(), 1),
+            Err(TrieError::WordTooLong { len: 6, max: 2 })
+        );
+        assert_eq!(
+            trie.insert_keyed("", "A".to_owned(), 1),
+            Err(TrieError::EmptyWord)
+        );
+    }
+
+    #[test]
+    fn test_min_query_len_applies_to_search_variants() {
+        let mut trie = WeightedTrie::with_min_query_len(2);
+        trie.insert("pizza".to_owned(), 3);
+        assert!(trie.best("p").is_none());
+        assert!(trie.search_cow("p").is_empty());
+        assert!(trie.search_exhaustive("p").is_empty());
+        assert!(trie.search_paginated("p", 0, 5).is_empty());
+        assert!(trie.search_pin_exact("p", 5).is_empty());
+        assert!(trie.search_union_top_k(&["p"], 5).is_empty());
+        assert!(trie.smart_search("p", 5).is_empty());
+        assert_eq!(trie.best("pi"), Some(("pizza".to_owned(), 3)));
+        #[cfg(feature = "cache")]
+        assert!(trie.search_cached("p", 3).is_empty());
+        assert!(trie.contains("pizza"));
+    }
+
+    #[test]
+    fn test_remove_clears_side_maps() {
+        let mut trie = WeightedTrie::new();
+        trie.insert_with_id("pizza".to_owned(), 1, 7).unwrap();
+        trie.insert_categorized("pie".to_owned(), 1, 3).unwrap();
+        trie.insert_composite("pita".to_owned(), &[1, 100]).unwrap();
+        trie.insert_keyed("pasta", "🍝 Pasta".to_owned(), 1)
+            .unwrap();
+        for word in ["pizza", "pie", "pita", "🍝 Pasta"] {
+            assert!(trie.remove(word));
+            trie.insert(word.to_owned(), 1);
+        }
+        assert!(trie.search_ids("pi").is_empty());
+        assert!(trie.search_in_category("pi", 3, 5).is_empty());
+        assert_eq!(trie.get("pie").unwrap().category, None);
+        trie.insert_composite("pit".to_owned(), &[1, 1]).unwrap();
+        assert_eq!(trie.search_composite("pit", &[0.0, 1.0], 1), vec!["pit"]);
+        assert_eq!(trie.search("🍝"), vec!["🍝 Pasta"]);
+        assert_eq!(trie.get("🍝 Pasta").unwrap().weight, 1);
+        assert!(trie.words_sorted().contains(&"🍝 Pasta".to_owned()));
+
+        let mut deduped = WeightedTrie::with_dedup_policy(DedupPolicy::CaseInsensitive);
+        deduped.insert("Pizza".to_owned(), 1);
+        assert!(deduped.remove("Pizza"));
+        deduped.insert("pizza".to_owned(), 2);
+        assert_eq!(deduped.search("piz"), vec!["pizza"]);
+
+        let mut recent = WeightedTrie::with_tie_break(TieBreak::RecencyFirst);
+        recent.insert("pa".to_owned(), 1);
+        recent.insert("pb".to_owned(), 1);
+        assert!(recent.remove("pa"));
+        recent.insert("pa".to_owned(), 1);
+        recent.rebuild_suggestions();
+        assert_eq!(recent.search("p"), vec!["pa", "pb"]);
+
+        #[cfg(feature = "std")]
+        {
+            let mut timed = WeightedTrie::new();
+            let start = std::time::Instant::now();
+            timed.insert_at("pizza".to_owned(), 1, start).unwrap();
+            assert!(timed.remove("pizza"));
+            timed.insert("pizza".to_owned(), 1);
+            let later = start + std::time::Duration::from_secs(1);
+            assert_eq!(timed.search_since("p", later), vec!["pizza"]);
+        }
+    }
+
+    #[test]
+    fn test_insert_replace_resolves_stored_spelling() {
+        let mut trie = WeightedTrie::with_dedup_policy(DedupPolicy::CaseInsensitive);
+        trie.insert("Pizza".to_owned(), 3);
+        assert_eq!(trie.insert_replace("pizza".to_owned(), 10), Some(3));
+        assert_eq!(trie.search_top_k("Piz", 5), vec![("Pizza".to_owned(), 10)]);
+        assert!(trie.search("piz").is_empty());
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_insert_replace_nfc() {
+        let mut trie = WeightedTrie::with_nfc();
+        assert_eq!(trie.insert_replace("cafe\u{301}".to_owned(), 3), None);
+        assert_eq!(trie.insert_replace("cafe\u{301}".to_owned(), 5), Some(3));
+        assert_eq!(trie.search_top_k("caf", 5), vec![("café".to_owned(), 5)]);
+    }
+
+    #[test]
+    fn test_get_or_insert_finds_dedup_variant() {
+        let mut trie = WeightedTrie::with_dedup_policy(DedupPolicy::CaseInsensitive);
+        trie.insert("Pizza".to_owned(), 3);
+        assert_eq!(trie.get_or_insert("pizza".to_owned(), 10), 3);
+        assert_eq!(trie.search_top_k("Piz", 5), vec![("Pizza".to_owned(), 3)]);
+    }
+
+    #[test]
+    fn test_highlight_keyed_words() {
+        let mut trie = WeightedTrie::with_case_insensitive();
+        trie.insert_keyed("pizza", "🍕 Pizza".to_owned(), 9)
+            .unwrap();
+        trie.insert_keyed("pie", "Tarte".to_owned(), 1).unwrap();
+        assert_eq!(
+            trie.search_highlighted("pi", 5),
+            vec![("🍕 Pizza".to_owned(), 5..7), ("Tarte".to_owned(), 0..0)]
+        );
+        assert_eq!(trie.complete_suffix("pi", 5), vec!["zza", "Tarte"]);
+    }
+
+    #[test]
+    fn test_insert_chars_max_word_len() {
+        let mut trie = WeightedTrie::with_max_word_len(4);
+        trie.insert_chars(&['p', 'i', 'e'], "pie", 5);
+        trie.insert_chars(&['p', 'i', 'z', 'z', 'a'], "pizza", 10);
+        trie.insert_chars(&['p', 'i', 't', 'a'], "Pita Bread", 2);
+        assert_eq!(trie.search("pi"), vec!["pie", "Pita Bread"]);
+        assert!(!trie.contains("pizza"));
+        assert_eq!(
+            trie.try_insert("pizza".to_owned(), 10),
+            Err(TrieError::WordTooLong { len: 5, max: 4 })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_tsv_repeated_word() {
+        let mut trie = WeightedTrie::new();
+        trie.insert("pie".to_owned(), 5);
+        trie.insert("pizza".to_owned(), 10);
+        trie.insert("pie".to_owned(), 3);
+
+        let mut tsv = Vec::new();
+        trie.write_tsv(&mut tsv, TsvOrder::Weight).unwrap();
+        assert_eq!(
+            String::from_utf8(tsv.clone()).unwrap(),
+            "pizza\t10\npie\t5\n"
+        );
+        let parsed = WeightedTrie::from_lines_streaming(tsv.as_slice()).unwrap();
+        assert_eq!(parsed.export_weights(), trie.export_weights());
+        assert_eq!(parsed.search("pi"), vec!["pizza", "pie"]);
+    }
 }